@@ -0,0 +1,26 @@
+//! Passes the GNU installation directories, if given as `PATHOS_*`
+//! environment variables, through to `InstallDirs::compiled`.
+
+const VARS: &[&str] = &[
+    "PATHOS_PREFIX",
+    "PATHOS_EXEC_PREFIX",
+    "PATHOS_BINDIR",
+    "PATHOS_LIBDIR",
+    "PATHOS_LIBEXECDIR",
+    "PATHOS_DATADIR",
+    "PATHOS_SYSCONFDIR",
+    "PATHOS_LOCALSTATEDIR",
+    "PATHOS_RUNSTATEDIR",
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    for var in VARS {
+        println!("cargo:rerun-if-env-changed={}", var);
+
+        if let Ok(value) = std::env::var(var) {
+            println!("cargo:rustc-env={}={}", var, value);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! App-specific directories on Android's shared ("external") storage.
+//!
+//! These mirror `Context.getExternalFilesDir()`, `getExternalCacheDir()`,
+//! `getExternalMediaDirs()` and `getObbDir()`. Unlike the container, they
+//! survive the user clearing the app's cache and can be reached over USB.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use crate::Error;
+
+static EXTERNAL_STORAGE_ROOT: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+/// Where the primary shared storage of every user is mounted, unless
+/// overridden with `set_external_storage_root`.
+pub const DEFAULT_EXTERNAL_STORAGE_ROOT: &str = "/storage/emulated";
+
+/// Overrides the root under which each user's shared storage is found (by
+/// default `/storage/emulated`), e.g. for devices with unusual mounts.
+pub fn set_external_storage_root(path: PathBuf) {
+    *EXTERNAL_STORAGE_ROOT.write().unwrap() = Some(path);
+}
+
+/// Restores the default external storage root.
+pub fn reset_external_storage_root() {
+    *EXTERNAL_STORAGE_ROOT.write().unwrap() = None;
+}
+
+#[inline]
+pub fn external_storage_root() -> PathBuf {
+    EXTERNAL_STORAGE_ROOT
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_EXTERNAL_STORAGE_ROOT))
+}
+
+/// The current user's shared storage, e.g. `/storage/emulated/0`.
+#[inline]
+pub fn external_storage_dir() -> PathBuf {
+    let uid = unsafe { libc::getuid() };
+    external_storage_root().join(super::user::user_id(uid).to_string())
+}
+
+/// The app's directory on shared storage, `<storage>/Android/data/<package>`.
+///
+/// This is the root of `external:` IRIs.
+#[inline]
+pub fn external_app_dir() -> Result<PathBuf, Error> {
+    android_dir("data")
+}
+
+#[inline]
+pub fn external_files_dir() -> Result<PathBuf, Error> {
+    external_app_dir().map(|x| x.join("files"))
+}
+
+#[inline]
+pub fn external_cache_dir() -> Result<PathBuf, Error> {
+    external_app_dir().map(|x| x.join("cache"))
+}
+
+/// Media shared with other apps, `<storage>/Android/media/<package>`.
+#[inline]
+pub fn external_media_dir() -> Result<PathBuf, Error> {
+    android_dir("media")
+}
+
+/// Expansion files, `<storage>/Android/obb/<package>`.
+#[inline]
+pub fn external_obb_dir() -> Result<PathBuf, Error> {
+    android_dir("obb")
+}
+
+#[inline]
+fn android_dir(kind: &str) -> Result<PathBuf, Error> {
+    let package = super::user::package_name()?;
+    Ok(package_dir(&external_storage_dir(), kind, &package))
+}
+
+#[inline]
+fn package_dir(storage: &Path, kind: &str, package: &str) -> PathBuf {
+    storage.join("Android").join(kind).join(package)
+}
+
+/// `external:` IRIs, relative to `external_app_dir`.
+pub mod iri {
+    use crate::{iri::IriPathBuilder, Error};
+    use iref::IriBuf;
+    use std::path::Path;
+
+    #[inline]
+    fn external_dir<P: AsRef<Path>>(dir: &str, prefix: P) -> Result<IriBuf, Error> {
+        Ok(IriPathBuilder::new("external")?
+            .push(dir)?
+            .push_path(prefix)?
+            .directory()
+            .build()?)
+    }
+
+    #[inline]
+    pub fn external_files_dir() -> Result<IriBuf, Error> {
+        external_dir("files", "")
+    }
+
+    #[inline]
+    pub fn external_cache_dir() -> Result<IriBuf, Error> {
+        external_dir("cache", "")
+    }
+
+    #[inline]
+    pub fn app_external_files_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        external_dir("files", prefix)
+    }
+
+    #[inline]
+    pub fn app_external_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        external_dir("cache", prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_dirs() {
+        let storage = Path::new(DEFAULT_EXTERNAL_STORAGE_ROOT).join("10");
+        assert_eq!(
+            package_dir(&storage, "data", "com.example.app").join("files"),
+            PathBuf::from("/storage/emulated/10/Android/data/com.example.app/files")
+        );
+        assert_eq!(
+            package_dir(&storage, "media", "com.example.app"),
+            PathBuf::from("/storage/emulated/10/Android/media/com.example.app")
+        );
+    }
+
+    #[test]
+    fn external_iris() {
+        assert_eq!(
+            iri::external_files_dir().unwrap().as_str(),
+            "external:/files/"
+        );
+        assert_eq!(
+            iri::app_external_files_dir("Bad App").unwrap().as_str(),
+            "external:/files/Bad%20App/"
+        );
+    }
+}
@@ -1,2 +1,3 @@
+pub mod external;
 pub mod system;
 pub mod user;
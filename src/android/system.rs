@@ -1 +1,82 @@
+use std::path::Path;
 
+use crate::{AppDirs, Error};
+
+/// Android apps are sandboxed and have no system-wide directories of their
+/// own, so this always fails with `Error::NotFound`. It exists so that code
+/// written against `pathos::system::app_layout` still compiles for Android.
+pub fn app_layout<P: AsRef<Path>>(_prefix: P) -> Result<AppDirs, Error> {
+    Err(Error::NotFound("System directories"))
+}
+
+/// As with `app_layout`, every function here fails with `Error::NotFound`.
+pub mod iri {
+    use crate::Error;
+    use iref::IriBuf;
+    use std::path::Path;
+
+    #[inline]
+    fn not_found() -> Result<IriBuf, Error> {
+        Err(Error::NotFound("System directories"))
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: AsRef<Path>>(_prefix: P) -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: AsRef<Path>>(_prefix: P) -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn app_cache_dir<P: AsRef<Path>>(_prefix: P) -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn app_temporary_dir<P: AsRef<Path>>(_prefix: P) -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: AsRef<Path>>(_prefix: P) -> Result<IriBuf, Error> {
+        not_found()
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: AsRef<Path>>(_prefix: P) -> Result<IriBuf, Error> {
+        not_found()
+    }
+}
@@ -1,49 +1,488 @@
 use std::os::{raw::c_char, unix::ffi::OsStrExt};
 use std::{
-    ffi::{CStr, OsStr},
+    ffi::{CStr, OsStr, OsString},
     path::{Path, PathBuf},
 };
 
-use once_cell::sync::OnceCell;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    diagnose::{self, Provenance, Source},
+    layout::UserLayout,
+    AppDirs, DirKind, Error, Platform, Registry, UserDirs,
+};
+
+/// The directories of an Android app's container, mirroring the layout used
+/// by `android.content.Context`.
+pub struct Dirs {
+    home_dir: PathBuf,
+    files_dir: PathBuf,
+    cache_dir: PathBuf,
+    code_cache_dir: PathBuf,
+    no_backup_dir: PathBuf,
+    databases_dir: PathBuf,
+    shared_prefs_dir: PathBuf,
+}
+
+impl Dirs {
+    /// `Context.getFilesDir()`
+    pub fn files_dir(&self) -> &Path {
+        &self.files_dir
+    }
+
+    /// `Context.getCodeCacheDir()`
+    pub fn code_cache_dir(&self) -> &Path {
+        &self.code_cache_dir
+    }
+
+    /// `Context.getNoBackupFilesDir()`
+    pub fn no_backup_dir(&self) -> &Path {
+        &self.no_backup_dir
+    }
+
+    /// The parent of `Context.getDatabasePath()`
+    pub fn databases_dir(&self) -> &Path {
+        &self.databases_dir
+    }
+
+    /// Where `SharedPreferences` are stored.
+    pub fn shared_prefs_dir(&self) -> &Path {
+        &self.shared_prefs_dir
+    }
+}
+
+impl UserDirs for Dirs {
+    fn new() -> Result<Self, Error> {
+        let home_dir = container_path()?;
+
+        Ok(Self {
+            files_dir: home_dir.join("files"),
+            cache_dir: home_dir.join("cache"),
+            code_cache_dir: home_dir.join("code_cache"),
+            no_backup_dir: home_dir.join("no_backup"),
+            databases_dir: home_dir.join("databases"),
+            shared_prefs_dir: home_dir.join("shared_prefs"),
+            home_dir,
+        })
+    }
+
+    fn home_dir(&self) -> &Path {
+        &self.home_dir
+    }
+
+    fn data_dir(&self) -> &Path {
+        &self.files_dir
+    }
+
+    /// Android apps keep their configuration alongside their data.
+    fn config_dir(&self) -> &Path {
+        &self.files_dir
+    }
+
+    fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    fn state_dir(&self) -> &Path {
+        &self.no_backup_dir
+    }
+
+    fn log_dir(&self) -> &Path {
+        &self.no_backup_dir
+    }
+
+    fn runtime_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Apps can't install executables for the user.
+    fn executables_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    /// `java.io.tmpdir` points into the cache directory; `/data/local/tmp` is
+    /// not writable by apps.
+    fn temporary_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+}
+
+/// The directories for `prefix` inside the Android container, without
+/// creating them.
+///
+/// Data and config live under `files`, which is included in Auto Backup. Logs
+/// and state live under `no_backup`, and cache and temporary files under `cache`, which
+/// the system may clear when storage is low.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    let user = UserLayout::of(&Dirs::new()?);
+    Ok(Platform::Android
+        .app_layout(&user, prefix)
+        .with_provenance(app_provenance))
+}
+
+#[inline]
+pub fn home_dir() -> Result<PathBuf, Error> {
+    container_path()
+}
+
+#[inline]
+pub fn files_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.files_dir().to_path_buf())
+}
+
+#[inline]
+pub fn data_dir() -> Result<PathBuf, Error> {
+    files_dir()
+}
+
+#[inline]
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.cache_dir().to_path_buf())
+}
+
+#[inline]
+pub fn config_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.config_dir().to_path_buf())
+}
+
+#[inline]
+pub fn state_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.state_dir().to_path_buf())
+}
+
+#[inline]
+pub fn log_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.log_dir().to_path_buf())
+}
+
+#[inline]
+pub fn runtime_dir() -> Result<PathBuf, Error> {
+    Err(Error::NotFound("Runtime"))
+}
+
+#[inline]
+pub fn executables_dir() -> Result<PathBuf, Error> {
+    Err(Error::NotFound("Executables"))
+}
+
+#[inline]
+pub fn temporary_dir() -> Result<PathBuf, Error> {
+    cache_dir()
+}
+
+#[inline]
+pub fn code_cache_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.code_cache_dir().to_path_buf())
+}
+
+#[inline]
+pub fn no_backup_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.no_backup_dir().to_path_buf())
+}
+
+#[inline]
+pub fn databases_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.databases_dir().to_path_buf())
+}
+
+#[inline]
+pub fn shared_prefs_dir() -> Result<PathBuf, Error> {
+    Dirs::new().map(|x| x.shared_prefs_dir().to_path_buf())
+}
+
+static APPS: Lazy<Registry> = Lazy::new(|| Registry::new(|prefix| app_layout(prefix)));
+
+/// The registry behind `app_dirs` and the `app_*` functions in this module.
+#[inline]
+pub fn registry() -> &'static Registry {
+    &APPS
+}
+
+/// The directories for `prefix`, created on first use and shared after.
+#[inline]
+pub fn app_dirs<P: Into<PathBuf>>(prefix: P) -> Result<Arc<AppDirs>, Error> {
+    APPS.get(prefix)
+}
+
+#[inline]
+pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.data_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.config_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.log_dir().to_path_buf())
+}
 
 #[inline]
-pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    CONTAINER_PATH
-        .get()
-        .expect("no path set for container; call set_container_path.")
-        .join("cache")
-        .join(prefix)
+pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.cache_dir().to_path_buf())
 }
 
 #[inline]
-pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    std::env::temp_dir().join(prefix)
+pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.state_dir().to_path_buf())
 }
 
+#[inline]
+pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.temporary_dir().to_path_buf())
+}
+
+/// `container:` IRIs for the Android container layout. These do not depend
+/// on the container path having been set.
 pub mod iri {
-    use crate::{path::absolute::AbsolutePathBufExt, Error};
+    use crate::{iri::IriPathBuilder, Error};
     use iref::IriBuf;
-    use std::convert::TryInto;
+    use std::path::Path;
 
     #[inline]
-    pub fn app_cache_dir<P: AsRef<str>>(prefix: P) -> Result<IriBuf, Error> {
-        let mut iri = IriBuf::new("container:/cache/").unwrap();
-        for item in prefix.as_ref().split("/") {
-            iri.path_mut().push(item.try_into().unwrap());
+    fn container_dir<P: AsRef<Path>>(
+        dir: &str,
+        prefix: P,
+        extra: &[&str],
+    ) -> Result<IriBuf, Error> {
+        let mut builder = IriPathBuilder::container();
+        builder.push(dir)?.push_path(prefix)?;
+
+        for item in extra {
+            builder.push(item)?;
         }
-        iri.path_mut().open();
-        Ok(iri)
+
+        Ok(builder.directory().build()?)
+    }
+
+    #[inline]
+    pub fn home_dir() -> Result<IriBuf, Error> {
+        Ok(IriPathBuilder::container().build()?)
+    }
+
+    #[inline]
+    pub fn files_dir() -> Result<IriBuf, Error> {
+        container_dir("files", "", &[])
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        files_dir()
     }
 
     #[inline]
-    pub fn app_temporary_dir<P: AsRef<str>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_temporary_dir(prefix.as_ref())
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        container_dir("cache", "", &[])
     }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        files_dir()
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        no_backup_dir()
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        no_backup_dir()
+    }
+
+    #[inline]
+    pub fn runtime_dir() -> Result<IriBuf, Error> {
+        Err(Error::NotFound("Runtime"))
+    }
+
+    #[inline]
+    pub fn executables_dir() -> Result<IriBuf, Error> {
+        Err(Error::NotFound("Executables"))
+    }
+
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        cache_dir()
+    }
+
+    #[inline]
+    pub fn code_cache_dir() -> Result<IriBuf, Error> {
+        container_dir("code_cache", "", &[])
+    }
+
+    #[inline]
+    pub fn no_backup_dir() -> Result<IriBuf, Error> {
+        container_dir("no_backup", "", &[])
+    }
+
+    #[inline]
+    pub fn databases_dir() -> Result<IriBuf, Error> {
+        container_dir("databases", "", &[])
+    }
+
+    #[inline]
+    pub fn shared_prefs_dir() -> Result<IriBuf, Error> {
+        container_dir("shared_prefs", "", &[])
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        container_dir("files", prefix, &[])
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        container_dir("files", prefix, &["config"])
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        container_dir("no_backup", prefix, &["log"])
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        container_dir("no_backup", prefix, &["state"])
+    }
+
+    #[inline]
+    pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        container_dir("cache", prefix, &[])
+    }
+
+    #[inline]
+    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        container_dir("cache", prefix, &["tmp"])
+    }
+}
+
+static CONTAINER_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+/// Forgets the app directories cached by `app_dirs`. Android resolves the
+/// container on every call, so there is nothing else to reload.
+#[inline]
+pub fn reload() -> Result<(), Error> {
+    APPS.clear();
+    Ok(())
+}
+
+/// Environment variable consulted for the container path when none has been
+/// set explicitly.
+pub const CONTAINER_PATH_ENV: &str = "PATHOS_ANDROID_CONTAINER";
+
+/// Android assigns each user (profile) a range of this many uids.
+const PER_USER_RANGE: u32 = 100_000;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ContainerError {
+    #[error("Container path already set to: '{}'", .0.display())]
+    AlreadySet(PathBuf),
+
+    #[error("Container path is not absolute: '{}'", .0.display())]
+    NotAbsolute(PathBuf),
+}
+
+/// The app's container (its data directory, e.g. `/data/user/0/<package>`).
+///
+/// In order of preference, this is the path given to `set_container_path`,
+/// the value of `PATHOS_ANDROID_CONTAINER`, or the path derived from the
+/// package name of the current process.
+#[inline]
+pub fn container_path() -> Result<PathBuf, Error> {
+    let set = CONTAINER_PATH.read().unwrap().clone();
+
+    resolve_container_path(set, std::env::var_os(CONTAINER_PATH_ENV))
+        .or_else(discover_container_path)
+        .ok_or(Error::NotFound("Container; call `set_container_path`"))
+}
+
+/// The set path if there is one, otherwise the value of the environment
+/// variable if it is absolute.
+fn resolve_container_path(set: Option<PathBuf>, env: Option<OsString>) -> Option<PathBuf> {
+    set.or_else(|| env.map(PathBuf::from).filter(|x| x.is_absolute()))
+}
+
+/// The package name of the current process, read from `/proc/self/cmdline`
+/// once.
+static PACKAGE_NAME: Lazy<Option<String>> = Lazy::new(|| {
+    let cmdline = std::fs::read("/proc/self/cmdline").ok()?;
+    package_name_from_cmdline(&cmdline).map(str::to_string)
+});
+
+/// Derives the container path from the package name in `/proc/self/cmdline`
+/// and the user id of the current process.
+pub fn discover_container_path() -> Option<PathBuf> {
+    let package = PACKAGE_NAME.as_deref()?;
+    let uid = unsafe { libc::getuid() };
+    Some(container_path_for_package(package, uid))
+}
+
+/// How the app directory of each kind is chosen, for `AppDirs::explain`:
+/// that of the user directory it is laid out within.
+fn app_provenance(kind: DirKind) -> Provenance {
+    diagnose::app_provenance(Platform::Android, kind, provenance)
+}
+
+/// How the directory of each kind is chosen, for `pathos::diagnose`. Every
+/// directory is inside the container.
+pub(crate) fn provenance(kind: DirKind) -> Provenance {
+    match kind {
+        DirKind::Runtime | DirKind::Executables => Provenance::default(),
+        _ if CONTAINER_PATH.read().unwrap().is_some() => Provenance::new(Source::Override),
+        _ => Provenance::env(CONTAINER_PATH_ENV, Source::Sandbox),
+    }
+}
+
+/// The container path Android assigns to `package` when run as `uid`:
+/// `/data/user/<uid / 100000>/<package>`.
+#[inline]
+pub fn container_path_for_package(package: &str, uid: u32) -> PathBuf {
+    PathBuf::from("/data/user")
+        .join(user_id(uid).to_string())
+        .join(package)
+}
+
+/// The Android user (profile) a uid belongs to.
+#[inline]
+pub(crate) fn user_id(uid: u32) -> u32 {
+    uid / PER_USER_RANGE
 }
 
-pub(crate) static CONTAINER_PATH: OnceCell<PathBuf> = OnceCell::new();
+/// The app's package name, taken from the final component of its container
+/// path.
+#[inline]
+pub fn package_name() -> Result<String, Error> {
+    container_path()?
+        .file_name()
+        .and_then(|x| x.to_str())
+        .map(str::to_string)
+        .ok_or(Error::NotFound("Package name"))
+}
+
+/// App processes are named after their package, optionally followed by
+/// `:<process>` for additional processes.
+fn package_name_from_cmdline(cmdline: &[u8]) -> Option<&str> {
+    let name = cmdline.split(|x| *x == 0).next()?;
+    let name = std::str::from_utf8(name).ok()?;
+    let package = name.split(':').next()?;
+
+    let is_valid = package.contains('.')
+        && package.split('.').all(|segment| {
+            let mut chars = segment.chars();
+            matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+
+    if is_valid {
+        Some(package)
+    } else {
+        None
+    }
+}
 
 #[no_mangle]
 unsafe extern "C" fn pathos_set_container_path(container_path: *const c_char) {
@@ -56,12 +495,123 @@ unsafe extern "C" fn pathos_set_container_path(container_path: *const c_char) {
     let os_str = OsStr::from_bytes(c_str.to_bytes());
     let path: &Path = os_str.as_ref();
 
-    let _ = CONTAINER_PATH.set(path.to_path_buf()).ok();
+    if let Err(e) = set_container_path(path.to_path_buf()) {
+        log::error!("{}", e);
+    }
+}
+
+/// Sets the container path.
+///
+/// Setting the same path again succeeds; setting a different path fails with
+/// the previously set path, unless `reset_container_path` is called first.
+pub fn set_container_path(path: PathBuf) -> Result<(), ContainerError> {
+    if !path.is_absolute() {
+        return Err(ContainerError::NotAbsolute(path));
+    }
+
+    replace_container_path(&mut CONTAINER_PATH.write().unwrap(), path)
+}
+
+/// Stores `path` in `slot`, unless a different path is already there.
+fn replace_container_path(slot: &mut Option<PathBuf>, path: PathBuf) -> Result<(), ContainerError> {
+    match slot.as_ref() {
+        Some(existing) if *existing != path => {
+            return Err(ContainerError::AlreadySet(existing.to_path_buf()))
+        }
+        Some(_) => {}
+        None => {
+            log::info!("Container path set to: '{}'", path.display());
+            *slot = Some(path);
+        }
+    }
 
-    log::info!("Container path set to: '{}'", path.display());
+    Ok(())
 }
 
-pub fn set_container_path(path: PathBuf) {
-    let _ = CONTAINER_PATH.set(path.to_path_buf()).ok();
-    log::info!("Container path set to: '{}'", path.display());
+/// Clears a container path set with `set_container_path`, so that it may be
+/// set again. This is mostly useful for tests.
+pub fn reset_container_path() {
+    *CONTAINER_PATH.write().unwrap() = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_iris() {
+        assert_eq!(iri::files_dir().unwrap().as_str(), "container:/files/");
+        assert_eq!(
+            iri::app_temporary_dir("Special Company/Bad App")
+                .unwrap()
+                .as_str(),
+            "container:/cache/Special%20Company/Bad%20App/tmp/"
+        );
+        assert!(iri::app_data_dir("../escape").is_err());
+    }
+
+    #[test]
+    fn package_name() {
+        assert_eq!(
+            package_name_from_cmdline(b"com.example.app\0"),
+            Some("com.example.app")
+        );
+        assert_eq!(
+            package_name_from_cmdline(b"com.example.app:remote\0"),
+            Some("com.example.app")
+        );
+        assert_eq!(package_name_from_cmdline(b"/usr/bin/cargo\0test\0"), None);
+        assert_eq!(package_name_from_cmdline(b"zygote64\0"), None);
+    }
+
+    #[test]
+    fn package_container_path() {
+        assert_eq!(
+            container_path_for_package("com.example.app", 10_123),
+            PathBuf::from("/data/user/0/com.example.app")
+        );
+        assert_eq!(
+            container_path_for_package("com.example.app", 1_010_123),
+            PathBuf::from("/data/user/10/com.example.app")
+        );
+    }
+
+    #[test]
+    fn set_and_reset() {
+        let mut slot = None;
+        let first = PathBuf::from("/data/user/0/com.example.first");
+        let second = PathBuf::from("/data/user/0/com.example.second");
+
+        replace_container_path(&mut slot, first.clone()).unwrap();
+        replace_container_path(&mut slot, first.clone()).unwrap();
+        assert_eq!(slot.as_ref(), Some(&first));
+
+        match replace_container_path(&mut slot, second.clone()) {
+            Err(ContainerError::AlreadySet(existing)) => assert_eq!(existing, first),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert!(set_container_path(PathBuf::from("relative")).is_err());
+
+        slot = None;
+        replace_container_path(&mut slot, second.clone()).unwrap();
+        assert_eq!(slot, Some(second));
+    }
+
+    #[test]
+    fn resolve() {
+        let set = PathBuf::from("/data/user/0/com.example.set");
+        let env = OsString::from("/data/user/0/com.example.env");
+
+        assert_eq!(
+            resolve_container_path(Some(set.clone()), Some(env.clone())),
+            Some(set)
+        );
+        assert_eq!(
+            resolve_container_path(None, Some(env)),
+            Some(PathBuf::from("/data/user/0/com.example.env"))
+        );
+        assert_eq!(resolve_container_path(None, Some("relative".into())), None);
+        assert_eq!(resolve_container_path(None, None), None);
+    }
 }
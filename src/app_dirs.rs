@@ -0,0 +1,212 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    diagnose::{Provenance, Report},
+    DirIter, DirKind, Error,
+};
+
+/// The directories of one app, as laid out by the `app_layout` function of
+/// one of the platform modules, such as `pathos::user::app_layout`.
+///
+/// This is a plain value: it can be cloned, compared, hashed, sent across
+/// threads and, with the `serde` feature, serialized. Building one doesn't
+/// touch the filesystem; call `create` for that.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AppDirs {
+    data_dir: PathBuf,
+    config_dir: PathBuf,
+    cache_dir: PathBuf,
+    log_dir: PathBuf,
+    state_dir: PathBuf,
+    temporary_dir: PathBuf,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    provenance: ProvenanceFn,
+}
+
+impl AppDirs {
+    pub(crate) fn new(
+        data_dir: PathBuf,
+        config_dir: PathBuf,
+        cache_dir: PathBuf,
+        log_dir: PathBuf,
+        state_dir: PathBuf,
+        temporary_dir: PathBuf,
+    ) -> AppDirs {
+        AppDirs {
+            data_dir,
+            config_dir,
+            cache_dir,
+            log_dir,
+            state_dir,
+            temporary_dir,
+            provenance: ProvenanceFn::default(),
+        }
+    }
+
+    /// Sets where `explain` says each directory came from.
+    #[inline]
+    pub(crate) fn with_provenance(mut self, provenance: fn(DirKind) -> Provenance) -> AppDirs {
+        self.provenance = ProvenanceFn(Some(provenance));
+        self
+    }
+
+    /// The current user's directories for `prefix`, as laid out by the
+    /// current `Backend`, created if missing.
+    ///
+    /// `pathos::user::app_dirs` does the same for the native backend, but
+    /// only once per prefix.
+    pub fn user<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+        let dirs = crate::backend().app_layout(prefix.as_ref())?;
+        dirs.create()?;
+        Ok(dirs)
+    }
+
+    /// The system-wide directories for `prefix`, as laid out by the current
+    /// `Backend`, created if missing. This usually requires elevated
+    /// privileges.
+    pub fn system<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+        let dirs = crate::backend().system_app_layout(prefix.as_ref())?;
+        dirs.create()?;
+        Ok(dirs)
+    }
+
+    #[inline]
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    #[inline]
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    #[inline]
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    #[inline]
+    pub fn log_dir(&self) -> &Path {
+        &self.log_dir
+    }
+
+    #[inline]
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+
+    #[inline]
+    pub fn temporary_dir(&self) -> &Path {
+        &self.temporary_dir
+    }
+
+    /// The directory of the given kind, or `None` for kinds an app doesn't
+    /// have (`Home`, `Runtime` and `Executables`).
+    pub fn dir(&self, kind: DirKind) -> Option<&Path> {
+        match kind {
+            DirKind::Data => Some(self.data_dir()),
+            DirKind::Config => Some(self.config_dir()),
+            DirKind::Cache => Some(self.cache_dir()),
+            DirKind::Log => Some(self.log_dir()),
+            DirKind::State => Some(self.state_dir()),
+            DirKind::Temporary => Some(self.temporary_dir()),
+            DirKind::Home | DirKind::Runtime | DirKind::Executables => None,
+        }
+    }
+
+    /// Every directory, in the order of `DirKind::APP`.
+    #[inline]
+    pub fn iter(&self) -> DirIter<'_> {
+        DirIter::new(self)
+    }
+
+    /// Explains where each directory came from and what state it is in.
+    pub fn explain(&self) -> Report {
+        match self.provenance.0 {
+            Some(provenance) => Report::inspect(self.iter(), provenance),
+            None => Report::inspect(self.iter(), |_| Provenance::default()),
+        }
+    }
+
+    /// Creates every directory, including any missing parents.
+    pub fn create(&self) -> Result<(), Error> {
+        for (_, dir) in self.iter() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
+            })?;
+        }
+        // TODO: set tmp writable only by creator.
+
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a AppDirs {
+    type Item = (DirKind, &'a Path);
+    type IntoIter = DirIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Where an `AppDirs` came from, for `explain`. It isn't part of the value:
+/// two `AppDirs` with the same paths are equal however they were built.
+#[derive(Clone, Copy, Default)]
+struct ProvenanceFn(Option<fn(DirKind) -> Provenance>);
+
+impl PartialEq for ProvenanceFn {
+    #[inline]
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ProvenanceFn {}
+
+impl Hash for ProvenanceFn {
+    #[inline]
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl fmt::Debug for ProvenanceFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Some(..)" } else { "None" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(root: &str) -> AppDirs {
+        let root = PathBuf::from(root);
+        AppDirs::new(
+            root.join("data"),
+            root.join("config"),
+            root.join("cache"),
+            root.join("log"),
+            root.join("state"),
+            root.join("tmp"),
+        )
+    }
+
+    #[test]
+    fn value() {
+        let a = fixed("/a");
+        let b = a.clone().with_provenance(|_| Provenance::default());
+        assert_eq!(a, b);
+        assert_ne!(a, fixed("/b"));
+        assert_eq!(a.config_dir(), Path::new("/a/config"));
+
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<AppDirs>();
+    }
+}
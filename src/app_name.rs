@@ -0,0 +1,137 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::path::portable::{check_portable_file_name, portable_file_name, PortableNameError};
+
+/// An app's directory prefix, checked to be valid on every platform.
+///
+/// It is one or more names separated by `/`, such as `Special Company/Bad
+/// App`, each of which passes `check_portable_file_name`. It converts to a
+/// path with the host's separator, so it can be passed anywhere a prefix is
+/// taken.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AppName {
+    name: String,
+    path: PathBuf,
+}
+
+impl AppName {
+    pub fn new(name: &str) -> Result<AppName, PortableNameError> {
+        for part in name.split('/') {
+            check_portable_file_name(part)?;
+        }
+
+        Ok(AppName::from_parts(name.split('/')))
+    }
+
+    /// Makes any string, such as a URL or a window title, into a valid name
+    /// with `portable_file_name`. Empty parts are dropped.
+    pub fn sanitize(name: &str) -> AppName {
+        let parts = name
+            .split('/')
+            .filter(|x| !x.trim().is_empty())
+            .map(portable_file_name)
+            .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            AppName::from_parts(std::iter::once("_"))
+        } else {
+            AppName::from_parts(parts.iter().map(|x| &**x))
+        }
+    }
+
+    /// The name, with `/` between its parts.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn as_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn parts(&self) -> impl Iterator<Item = &str> {
+        self.name.split('/')
+    }
+
+    fn from_parts<'a>(parts: impl Iterator<Item = &'a str>) -> AppName {
+        let parts = parts.collect::<Vec<_>>();
+
+        AppName {
+            name: parts.join("/"),
+            path: parts.iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for AppName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl FromStr for AppName {
+    type Err = PortableNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AppName::new(s)
+    }
+}
+
+impl TryFrom<&str> for AppName {
+    type Error = PortableNameError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        AppName::new(value)
+    }
+}
+
+impl AsRef<Path> for AppName {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl From<AppName> for PathBuf {
+    #[inline]
+    fn from(name: AppName) -> PathBuf {
+        name.path
+    }
+}
+
+impl From<&AppName> for PathBuf {
+    #[inline]
+    fn from(name: &AppName) -> PathBuf {
+        name.path.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_name() {
+        let name = AppName::new("Special Company/Bad App").unwrap();
+        assert_eq!(name.as_path(), Path::new("Special Company").join("Bad App"));
+        assert_eq!(
+            name.parts().collect::<Vec<_>>(),
+            ["Special Company", "Bad App"]
+        );
+
+        assert!(AppName::new("nul").is_err());
+        assert!(AppName::new("a//b").is_err());
+        assert!(AppName::new(r"a\b").is_err());
+
+        assert_eq!(
+            AppName::sanitize("/My App: Deluxe/ /aux/").as_str(),
+            "My App- Deluxe/aux_"
+        );
+    }
+}
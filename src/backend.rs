@@ -0,0 +1,202 @@
+//! Pluggable resolution of the user's and system's directories.
+//!
+//! `AppDirs::user` and `AppDirs::system` ask the backend set with
+//! `set_backend`, or `Native` if none was set, so that an embedder can
+//! supply the conventions of an environment `pathos` doesn't know about,
+//! such as a game console SDK, an appliance image or a custom sandbox.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    diagnose::Provenance,
+    layout::{Roots, UserLayout},
+    AppDirs, DirKind, Error, Platform,
+};
+
+static BACKEND: RwLock<Option<Arc<dyn Backend>>> = RwLock::new(None);
+static NATIVE: Lazy<Arc<dyn Backend>> = Lazy::new(|| Arc::new(Native));
+
+/// Resolves directories for one environment.
+pub trait Backend: Send + Sync {
+    /// The current user's base directories.
+    fn user_layout(&self) -> Result<UserLayout, Error>;
+
+    /// The current user's directories for an app, without creating them.
+    fn app_layout(&self, prefix: &Path) -> Result<AppDirs, Error>;
+
+    /// The system-wide directories for an app, without creating them.
+    fn system_app_layout(&self, prefix: &Path) -> Result<AppDirs, Error>;
+
+    /// How the user directory of each kind is chosen, for
+    /// `pathos::diagnose`. Unknown by default.
+    fn provenance(&self, _kind: DirKind) -> Provenance {
+        Provenance::default()
+    }
+}
+
+/// Replaces the backend returned by `backend`. Call this at startup, before
+/// anything resolves directories through it.
+///
+/// This changes `AppDirs::user`, `AppDirs::system`, `Scope` and
+/// `pathos::diagnose`. The free functions and registries in `pathos::user`
+/// and `pathos::system` always use the native conventions.
+pub fn set_backend<B: Backend + 'static>(backend: B) {
+    *BACKEND.write().unwrap() = Some(Arc::new(backend));
+}
+
+/// The backend set with `set_backend`, or `Native`.
+pub fn backend() -> Arc<dyn Backend> {
+    match &*BACKEND.read().unwrap() {
+        Some(backend) => Arc::clone(backend),
+        None => Arc::clone(&NATIVE),
+    }
+}
+
+/// The built-in backend for the target this crate was compiled for, i.e.
+/// `pathos::user` and `pathos::system`. On targets without one, every method
+/// fails with `Error::NotFound`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Native;
+
+#[cfg(any(unix, windows, target_os = "wasi"))]
+impl Backend for Native {
+    fn user_layout(&self) -> Result<UserLayout, Error> {
+        use crate::UserDirs;
+
+        crate::user::Dirs::new().map(|x| UserLayout::of(&x))
+    }
+
+    fn app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        crate::user::app_layout(prefix)
+    }
+
+    fn system_app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        crate::system::app_layout(prefix)
+    }
+
+    fn provenance(&self, kind: DirKind) -> Provenance {
+        crate::user::provenance(kind)
+    }
+}
+
+#[cfg(not(any(unix, windows, target_os = "wasi")))]
+impl Backend for Native {
+    fn user_layout(&self) -> Result<UserLayout, Error> {
+        Err(Error::NotFound("Backend"))
+    }
+
+    fn app_layout(&self, _prefix: &Path) -> Result<AppDirs, Error> {
+        Err(Error::NotFound("Backend"))
+    }
+
+    fn system_app_layout(&self, _prefix: &Path) -> Result<AppDirs, Error> {
+        Err(Error::NotFound("Backend"))
+    }
+}
+
+/// The XDG Base Directory backend, i.e. `pathos::linux`. It is the native
+/// backend on Linux, the BSDs, illumos, Redox and WASI.
+#[cfg(any(
+    feature = "linux",
+    target_os = "wasi",
+    all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )
+))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Xdg;
+
+#[cfg(any(
+    feature = "linux",
+    target_os = "wasi",
+    all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )
+))]
+impl Backend for Xdg {
+    fn user_layout(&self) -> Result<UserLayout, Error> {
+        use crate::UserDirs;
+
+        crate::linux::user::Dirs::new().map(|x| UserLayout::of(&x))
+    }
+
+    fn app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        crate::linux::user::app_layout(prefix)
+    }
+
+    fn system_app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        crate::linux::system::app_layout(prefix)
+    }
+
+    fn provenance(&self, kind: DirKind) -> Provenance {
+        crate::linux::user::provenance(kind)
+    }
+}
+
+/// A backend that lays out a platform's directories from fixed roots, with
+/// the functions in `pathos::layout`, without consulting the environment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fixed {
+    platform: Platform,
+    roots: Roots,
+    system_root: Option<PathBuf>,
+}
+
+impl Fixed {
+    pub fn new(platform: Platform, roots: Roots) -> Fixed {
+        Fixed {
+            platform,
+            roots,
+            system_root: None,
+        }
+    }
+
+    /// Sets the root that system directories are found under; see
+    /// `Platform::system_app_layout`.
+    pub fn system_root<P: Into<PathBuf>>(&mut self, root: P) -> &mut Self {
+        self.system_root = Some(root.into());
+        self
+    }
+}
+
+impl Backend for Fixed {
+    fn user_layout(&self) -> Result<UserLayout, Error> {
+        Ok(self.platform.user_layout(&self.roots))
+    }
+
+    fn app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        Ok(self.platform.app_layout(&self.user_layout()?, prefix))
+    }
+
+    fn system_app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        self.platform
+            .system_app_layout(self.system_root.as_deref(), prefix)
+            .ok_or(Error::NotFound("System directories"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed() {
+        let root = Path::new("/sysroot");
+
+        let mut backend = Fixed::new(Platform::Linux, Roots::new(root.join("home")));
+        backend.system_root(root);
+
+        let dirs = backend.app_layout(Path::new("app")).unwrap();
+        assert_eq!(dirs.config_dir(), root.join("home/.config/app"));
+
+        let system = backend.system_app_layout(Path::new("app")).unwrap();
+        assert_eq!(system.state_dir(), root.join("var/lib/app"));
+    }
+}
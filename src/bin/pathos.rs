@@ -0,0 +1,489 @@
+//! Prints the directories `pathos` resolves, for shell scripts, Makefiles and
+//! packaging hooks.
+
+use std::{
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use pathos::{
+    diagnose::{Entry, Report},
+    iri::IriBufExt,
+    path::absolute::AbsolutePathExt,
+    AppDirs, DirKind, Error, Platform, UserDirs,
+};
+
+const USAGE: &str = "\
+Usage: pathos [--format plain|json|export] <command>
+
+Commands:
+    user [KIND]                 The user's base directories
+    app <APP> [KIND]            An app's user directories
+    system <APP> [KIND]         An app's system-wide directories
+    iri <PATH>                  Converts an absolute path to an IRI
+    path <IRI>                  Converts a file: IRI to a path, or a container:
+                                IRI on iOS and Android
+    diagnose [APP]              Explains how each directory was resolved
+
+<APP> is either a prefix, such as `Special Company/App`, or
+`--project QUALIFIER ORGANIZATION APPLICATION` for the platform's
+conventional prefix.
+
+KIND is one of: home, config, data, cache, state, log, runtime,
+executables or temporary.
+
+Options:
+    --format FORMAT    plain (default), json or export
+    --json             Same as --format json
+    --export           Same as --format export, e.g. for `eval`
+    -h, --help         Prints this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Plain,
+    Json,
+    Export,
+}
+
+enum Failure {
+    /// The arguments were invalid.
+    Usage(String),
+
+    /// The arguments were fine, but the answer couldn't be found.
+    Failed(String),
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Usage(message)
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(message: &str) -> Self {
+        Failure::Usage(message.to_string())
+    }
+}
+
+#[inline]
+fn failed<E: std::fmt::Display>(e: E) -> Failure {
+    Failure::Failed(e.to_string())
+}
+
+struct Args {
+    format: Format,
+    rest: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, Failure> {
+    let mut format = Format::Plain;
+    let mut rest = vec![];
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            "--json" => format = Format::Json,
+            "--export" => format = Format::Export,
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("plain") => Format::Plain,
+                    Some("json") => Format::Json,
+                    Some("export") => Format::Export,
+                    Some(other) => return Err(format!("unknown format: '{}'", other).into()),
+                    None => return Err("--format requires a value".into()),
+                }
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    Ok(Args { format, rest })
+}
+
+/// Takes the `<APP>` argument from the front of `args`.
+fn take_prefix(args: &mut Vec<String>) -> Result<PathBuf, Failure> {
+    if args.first().map(|x| &**x) == Some("--project") {
+        if args.len() < 4 {
+            return Err("--project requires QUALIFIER ORGANIZATION APPLICATION".into());
+        }
+
+        let parts = args.drain(..4).skip(1).collect::<Vec<_>>();
+        return Ok(Platform::current().project_prefix(&parts[0], &parts[1], &parts[2]));
+    }
+
+    if args.is_empty() {
+        return Err("missing app prefix".into());
+    }
+
+    Ok(PathBuf::from(args.remove(0)))
+}
+
+fn take_kind(args: &mut Vec<String>) -> Result<Option<DirKind>, Failure> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    args.remove(0)
+        .parse()
+        .map(Some)
+        .map_err(|e: pathos::UnknownDirKind| Failure::Usage(e.to_string()))
+}
+
+fn expect_empty(args: &[String]) -> Result<(), Failure> {
+    match args.first() {
+        Some(arg) => Err(format!("unexpected argument: '{}'", arg).into()),
+        None => Ok(()),
+    }
+}
+
+fn print_dirs(format: Format, scope: &str, dirs: &[(DirKind, &Path)]) -> Result<(), Failure> {
+    match format {
+        Format::Plain => {
+            for (kind, path) in dirs {
+                println!("{:<12} {}", kind, path.display());
+            }
+        }
+        Format::Json => {
+            let fields = dirs
+                .iter()
+                .map(|(kind, path)| {
+                    Ok(format!(
+                        "  {}: {}",
+                        json_string(kind.as_str()),
+                        json_path(path)?
+                    ))
+                })
+                .collect::<Result<Vec<_>, Failure>>()?;
+            println!("{{\n{}\n}}", fields.join(",\n"));
+        }
+        Format::Export => {
+            let lines = dirs
+                .iter()
+                .map(|(kind, path)| {
+                    Ok(format!(
+                        "export PATHOS_{}_{}_DIR={}",
+                        scope.to_uppercase(),
+                        kind.as_str().to_uppercase(),
+                        shell_quote(utf8(path)?)
+                    ))
+                })
+                .collect::<Result<Vec<_>, Failure>>()?;
+            println!("{}", lines.join("\n"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a single directory on its own, so that `$(pathos user config)`
+/// works without further parsing.
+fn print_dir(
+    format: Format,
+    scope: &str,
+    kind: DirKind,
+    path: Option<&Path>,
+) -> Result<(), Failure> {
+    let path = path.ok_or_else(|| failed(format!("no {} directory on this platform", kind)))?;
+
+    match format {
+        Format::Plain => {
+            println!("{}", path.display());
+            Ok(())
+        }
+        _ => print_dirs(format, scope, &[(kind, path)]),
+    }
+}
+
+fn print_value(format: Format, name: &str, value: &str) {
+    match format {
+        Format::Plain => println!("{}", value),
+        Format::Json => println!("{}", json_string(value)),
+        Format::Export => println!("export {}={}", name, shell_quote(value)),
+    }
+}
+
+fn user(format: Format, mut args: Vec<String>) -> Result<(), Failure> {
+    let kind = take_kind(&mut args)?;
+    expect_empty(&args)?;
+
+    let dirs = pathos::user::Dirs::new().map_err(failed)?;
+
+    match kind {
+        Some(kind) => print_dir(format, "user", kind, dirs.dir(kind)),
+        None => {
+            let all = DirKind::ALL
+                .iter()
+                .filter_map(|kind| dirs.dir(*kind).map(|path| (*kind, path)))
+                .collect::<Vec<_>>();
+            print_dirs(format, "user", &all)
+        }
+    }
+}
+
+fn app(
+    format: Format,
+    scope: &str,
+    layout: fn(&Path) -> Result<AppDirs, Error>,
+    mut args: Vec<String>,
+) -> Result<(), Failure> {
+    let prefix = take_prefix(&mut args)?;
+    let kind = take_kind(&mut args)?;
+    expect_empty(&args)?;
+
+    let dirs = layout(&prefix).map_err(failed)?;
+
+    match kind {
+        Some(kind) => print_dir(format, scope, kind, dirs.dir(kind)),
+        None => print_dirs(format, scope, &dirs.iter().collect::<Vec<_>>()),
+    }
+}
+
+fn iri(format: Format, mut args: Vec<String>) -> Result<(), Failure> {
+    if args.is_empty() {
+        return Err("missing path".into());
+    }
+    let path = args.remove(0);
+    expect_empty(&args)?;
+
+    let path = Path::new(&path);
+    let path = path.to_absolute_path().map_err(failed)?;
+
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    let iri = path.to_container_or_file_iri();
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    let iri = path.to_file_iri();
+
+    let iri = iri.map_err(failed)?;
+    print_value(format, "PATHOS_IRI", iri.as_str());
+    Ok(())
+}
+
+fn path(format: Format, mut args: Vec<String>) -> Result<(), Failure> {
+    if args.is_empty() {
+        return Err("missing IRI".into());
+    }
+    let iri = args.remove(0);
+    expect_empty(&args)?;
+
+    let iri =
+        iref::IriBuf::new(&iri).map_err(|e| Failure::Failed(format!("invalid IRI: {:?}", e)))?;
+    let path = iri.to_path_buf().map_err(failed)?;
+
+    match format {
+        Format::Plain => println!("{}", path.display()),
+        _ => print_value(format, "PATHOS_PATH", utf8(&path)?),
+    }
+
+    Ok(())
+}
+
+fn diagnose(format: Format, mut args: Vec<String>) -> Result<(), Failure> {
+    if format == Format::Export {
+        return Err("`diagnose` doesn't support --format export".into());
+    }
+
+    let app = if args.is_empty() {
+        None
+    } else {
+        Some(take_prefix(&mut args)?)
+    };
+    expect_empty(&args)?;
+
+    let mut reports = vec![("user", pathos::diagnose())];
+
+    if let Some(prefix) = app {
+        let dirs = pathos::user::app_layout(prefix).map_err(failed)?;
+        reports.push(("app", dirs.explain()));
+    }
+
+    match format {
+        Format::Json => {
+            let fields = reports
+                .iter()
+                .map(|(name, report)| {
+                    Ok(format!("  {}: {}", json_string(name), json_report(report)?))
+                })
+                .collect::<Result<Vec<_>, Failure>>()?;
+            println!("{{\n{}\n}}", fields.join(",\n"));
+        }
+        _ => {
+            for (name, report) in reports.iter() {
+                println!("[{}]\n{}", name, report);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn json_report(report: &Report) -> Result<String, Failure> {
+    let entries = report
+        .entries
+        .iter()
+        .map(|x| Ok(format!("    {}", json_entry(x)?)))
+        .collect::<Result<Vec<_>, Failure>>()?;
+    Ok(format!("[\n{}\n  ]", entries.join(",\n")))
+}
+
+fn json_entry(entry: &Entry) -> Result<String, Failure> {
+    let source = match &entry.provenance.source {
+        pathos::diagnose::Source::Env(name) => format!("env:{}", name),
+        other => format!("{:?}", other).to_lowercase(),
+    };
+
+    let fields = [
+        ("kind", json_string(entry.kind.as_str())),
+        (
+            "path",
+            json_option(entry.path.as_deref().map(json_path).transpose()?),
+        ),
+        (
+            "error",
+            json_option(entry.error.as_deref().map(json_string)),
+        ),
+        ("source", json_string(&source)),
+        (
+            "raw",
+            json_option(
+                entry
+                    .provenance
+                    .raw
+                    .as_ref()
+                    .map(|x| json_path(Path::new(x)))
+                    .transpose()?,
+            ),
+        ),
+        (
+            "rejected",
+            json_option(entry.provenance.rejected.as_deref().map(json_string)),
+        ),
+        ("exists", entry.status.exists.to_string()),
+        ("is_dir", entry.status.is_dir.to_string()),
+        ("writable", entry.status.writable.to_string()),
+        (
+            "symlink_target",
+            json_option(
+                entry
+                    .status
+                    .symlink_target
+                    .as_deref()
+                    .map(json_path)
+                    .transpose()?,
+            ),
+        ),
+        (
+            "other_filesystem",
+            entry.status.other_filesystem.to_string(),
+        ),
+    ];
+
+    let fields = fields
+        .iter()
+        .map(|(name, value)| format!("{}: {}", json_string(name), value))
+        .collect::<Vec<_>>();
+    Ok(format!("{{ {} }}", fields.join(", ")))
+}
+
+#[inline]
+fn json_option(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".into())
+}
+
+/// JSON strings can't hold arbitrary bytes, so paths that aren't valid
+/// UTF-8 are an error rather than being silently replaced.
+#[inline]
+fn json_path(path: &Path) -> Result<String, Failure> {
+    utf8(path).map(json_string)
+}
+
+/// A path as text for `--json` and `--export`, where a lossy conversion would
+/// point at a different directory.
+#[inline]
+fn utf8(path: &Path) -> Result<&str, Failure> {
+    path.to_str()
+        .ok_or_else(|| failed(format!("not valid UTF-8: {}", path.display())))
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Quotes a value for POSIX shells.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn run() -> Result<(), Failure> {
+    let Args { format, mut rest } = parse_args()?;
+
+    if rest.is_empty() {
+        return Err("missing command".into());
+    }
+
+    let command = rest.remove(0);
+    match &*command {
+        "user" => user(format, rest),
+        "app" => app(format, "app", |x| pathos::user::app_layout(x), rest),
+        "system" => app(format, "system", |x| pathos::system::app_layout(x), rest),
+        "iri" => iri(format, rest),
+        "path" => path(format, rest),
+        "diagnose" => diagnose(format, rest),
+        other => Err(format!("unknown command: '{}'", other).into()),
+    }
+}
+
+fn main() {
+    match run() {
+        Ok(()) => {}
+        Err(Failure::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            exit(2);
+        }
+        Err(Failure::Failed(message)) => {
+            eprintln!("error: {}", message);
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(json_string("a \"b\"\\\n"), r#""a \"b\"\\\n""#);
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/\xff"));
+        assert!(json_path(path).is_err());
+        assert_eq!(
+            json_path(Path::new("/tmp/a")).ok(),
+            Some(r#""/tmp/a""#.into())
+        );
+    }
+}
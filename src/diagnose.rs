@@ -0,0 +1,372 @@
+//! Reports explaining how each directory was resolved, for debugging
+//! reports such as "my config isn't picked up".
+
+use std::{
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{DirKind, Platform};
+
+/// Where a directory's location came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// Read from the named environment variable.
+    Env(&'static str),
+
+    /// Returned by an operating system API, such as Known Folders on Windows,
+    /// `NSFileManager` on Apple platforms or the password database.
+    System,
+
+    /// The app's sandbox container on iOS and Android.
+    Sandbox,
+
+    /// Set explicitly by the app, such as with
+    /// `android::user::set_container_path`.
+    Override,
+
+    /// A fixed default, or a location derived from another directory.
+    #[default]
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Env(name) => write!(f, "environment variable {}", name),
+            Source::System => f.write_str("system API"),
+            Source::Sandbox => f.write_str("sandbox container"),
+            Source::Override => f.write_str("override"),
+            Source::Default => f.write_str("default"),
+        }
+    }
+}
+
+/// How a directory's location was chosen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    pub source: Source,
+
+    /// The raw value that was consulted, such as the content of an
+    /// environment variable, even if it was rejected.
+    pub raw: Option<OsString>,
+
+    /// Why `raw` was rejected in favour of the default.
+    pub rejected: Option<String>,
+}
+
+impl Provenance {
+    #[inline]
+    pub(crate) fn new(source: Source) -> Provenance {
+        Provenance {
+            source,
+            raw: None,
+            rejected: None,
+        }
+    }
+
+    /// The provenance of a directory taken from the environment variable
+    /// `var` if it holds an absolute path, and from `fallback` otherwise.
+    pub(crate) fn env(var: &'static str, fallback: Source) -> Provenance {
+        Provenance::from_var(var, std::env::var_os(var), fallback)
+    }
+
+    /// `env`, given the variable's value.
+    fn from_var(var: &'static str, value: Option<OsString>, fallback: Source) -> Provenance {
+        match value {
+            Some(raw) if Path::new(&raw).is_absolute() => Provenance {
+                source: Source::Env(var),
+                raw: Some(raw),
+                rejected: None,
+            },
+            Some(raw) if raw.is_empty() => Provenance::new(fallback),
+            Some(raw) => Provenance {
+                source: fallback,
+                raw: Some(raw),
+                rejected: Some(format!("{} is not an absolute path", var)),
+            },
+            None => Provenance::new(fallback),
+        }
+    }
+}
+
+/// How an app directory of `kind` is chosen on `platform`, for
+/// `AppDirs::explain`: that of the user directory it is laid out within, as
+/// given by `base`.
+pub(crate) fn app_provenance(
+    platform: Platform,
+    kind: DirKind,
+    base: fn(DirKind) -> Provenance,
+) -> Provenance {
+    platform.app_base(kind).map(base).unwrap_or_default()
+}
+
+/// The state of a directory on disk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Status {
+    pub exists: bool,
+    pub is_dir: bool,
+
+    /// Whether the current process may create files in the directory.
+    pub writable: bool,
+
+    /// Where the directory points, if it is itself a symlink.
+    pub symlink_target: Option<PathBuf>,
+
+    /// Whether the directory is on a different filesystem from the home
+    /// directory. Always `false` on platforms other than Unix.
+    pub other_filesystem: bool,
+}
+
+impl Status {
+    pub fn inspect<P: AsRef<Path>>(path: P) -> Status {
+        let path = path.as_ref();
+
+        let symlink_target = std::fs::symlink_metadata(path)
+            .ok()
+            .filter(|x| x.file_type().is_symlink())
+            .and_then(|_| std::fs::read_link(path).ok());
+
+        let meta = match std::fs::metadata(path) {
+            Ok(meta) => meta,
+            Err(_) => {
+                return Status {
+                    symlink_target,
+                    ..Status::default()
+                }
+            }
+        };
+
+        Status {
+            exists: true,
+            is_dir: meta.is_dir(),
+            writable: is_writable(path, &meta),
+            symlink_target,
+            other_filesystem: is_other_filesystem(&meta),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[inline]
+fn is_writable(path: &Path, _meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+#[inline]
+fn is_writable(_path: &Path, meta: &std::fs::Metadata) -> bool {
+    !meta.permissions().readonly()
+}
+
+#[cfg(unix)]
+#[inline]
+fn is_other_filesystem(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let home = crate::backend()
+        .user_layout()
+        .ok()
+        .and_then(|x| std::fs::metadata(x.home_dir()).ok());
+
+    match home {
+        Some(home) => home.dev() != meta.dev(),
+        None => false,
+    }
+}
+
+#[cfg(not(unix))]
+#[inline]
+fn is_other_filesystem(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Everything known about one directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: DirKind,
+
+    /// The resolved location, or `None` if it couldn't be resolved.
+    pub path: Option<PathBuf>,
+
+    /// Why the location couldn't be resolved.
+    pub error: Option<String>,
+
+    pub provenance: Provenance,
+    pub status: Status,
+}
+
+impl Entry {
+    pub(crate) fn new(kind: DirKind, path: Result<&Path, String>, provenance: Provenance) -> Entry {
+        match path {
+            Ok(path) => Entry {
+                kind,
+                path: Some(path.to_path_buf()),
+                error: None,
+                provenance,
+                status: Status::inspect(path),
+            },
+            Err(e) => Entry {
+                kind,
+                path: None,
+                error: Some(e),
+                provenance,
+                status: Status::default(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.path, &self.error) {
+            (Some(path), _) => writeln!(f, "{:<12} {}", self.kind, path.display())?,
+            (None, Some(error)) => writeln!(f, "{:<12} unresolved: {}", self.kind, error)?,
+            (None, None) => writeln!(f, "{:<12} unresolved", self.kind)?,
+        }
+
+        write!(f, "{:<12} source: {}", "", self.provenance.source)?;
+        if let (Source::Env(_), Some(raw)) = (&self.provenance.source, &self.provenance.raw) {
+            write!(f, " = {:?}", raw)?;
+        }
+        writeln!(f)?;
+
+        if let Some(reason) = &self.provenance.rejected {
+            match &self.provenance.raw {
+                Some(raw) => writeln!(f, "{:<12} rejected: {:?} ({})", "", raw, reason)?,
+                None => writeln!(f, "{:<12} rejected: {}", "", reason)?,
+            }
+        }
+
+        if self.path.is_none() {
+            return Ok(());
+        }
+
+        let status = &self.status;
+        let state = match (status.exists, status.is_dir, status.writable) {
+            (false, _, _) => "missing",
+            (true, false, _) => "exists, not a directory",
+            (true, true, true) => "exists, writable",
+            (true, true, false) => "exists, read-only",
+        };
+        writeln!(f, "{:<12} status: {}", "", state)?;
+
+        if let Some(target) = &status.symlink_target {
+            writeln!(f, "{:<12} symlink to: {}", "", target.display())?;
+        }
+
+        if status.other_filesystem {
+            writeln!(
+                f,
+                "{:<12} on a different filesystem from the home directory",
+                ""
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A report on a set of directories, from `pathos::diagnose` or
+/// `AppDirs::explain`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub entries: Vec<Entry>,
+}
+
+impl Report {
+    /// Inspects each directory, taking its provenance from `provenance`.
+    pub fn inspect<'a, I, F>(dirs: I, provenance: F) -> Report
+    where
+        I: IntoIterator<Item = (DirKind, &'a Path)>,
+        F: Fn(DirKind) -> Provenance,
+    {
+        Report {
+            entries: dirs
+                .into_iter()
+                .map(|(kind, path)| Entry::new(kind, Ok(path), provenance(kind)))
+                .collect(),
+        }
+    }
+
+    /// The entry for the given kind, if the report has one.
+    pub fn get(&self, kind: DirKind) -> Option<&Entry> {
+        self.entries.iter().find(|x| x.kind == kind)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.entries.iter() {
+            fmt::Display::fmt(entry, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Explains how each of the current user's directories was resolved by the
+/// current `Backend`, and what state it is in.
+pub fn diagnose() -> Report {
+    let backend = crate::backend();
+    let layout = backend.user_layout();
+
+    let entries = DirKind::ALL
+        .iter()
+        .map(|kind| {
+            let path = match &layout {
+                Ok(layout) => layout.dir(*kind).ok_or_else(|| "not available".to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            Entry::new(*kind, path, backend.provenance(*kind))
+        })
+        .collect();
+
+    Report { entries }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn status() {
+        let dir = std::env::temp_dir().join(format!("pathos-diagnose-{}", std::process::id()));
+        let link = dir.with_extension("link");
+        std::fs::create_dir_all(&dir).unwrap();
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+
+        let status = Status::inspect(&link);
+        assert!(status.exists && status.is_dir && status.writable);
+        assert_eq!(status.symlink_target.as_deref(), Some(&*dir));
+
+        std::fs::remove_file(&link).unwrap();
+        std::fs::remove_dir(&dir).unwrap();
+
+        assert!(!Status::inspect(&dir).exists);
+    }
+
+    #[test]
+    fn env_provenance() {
+        let provenance = Provenance::from_var("VAR", Some("relative/dir".into()), Source::Default);
+        assert_eq!(provenance.source, Source::Default);
+        assert_eq!(provenance.raw, Some("relative/dir".into()));
+        assert!(provenance.rejected.is_some());
+
+        let provenance = Provenance::from_var("VAR", Some("/srv/dir".into()), Source::Default);
+        assert_eq!(provenance.source, Source::Env("VAR"));
+        assert!(provenance.rejected.is_none());
+
+        let provenance = Provenance::from_var("VAR", None, Source::System);
+        assert_eq!(provenance, Provenance::new(Source::System));
+    }
+}
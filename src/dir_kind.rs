@@ -0,0 +1,144 @@
+use std::{fmt, path::Path, str::FromStr};
+
+/// The kinds of directory known to `AppDirs` and `UserDirs`, for code that
+/// treats them uniformly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DirKind {
+    Home,
+    Config,
+    Data,
+    Cache,
+    State,
+    Log,
+    Runtime,
+    Executables,
+    Temporary,
+}
+
+impl DirKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [DirKind; 9] = [
+        DirKind::Home,
+        DirKind::Config,
+        DirKind::Data,
+        DirKind::Cache,
+        DirKind::State,
+        DirKind::Log,
+        DirKind::Runtime,
+        DirKind::Executables,
+        DirKind::Temporary,
+    ];
+
+    /// The kinds every `AppDirs` has, in the order they are created.
+    pub const APP: [DirKind; 6] = [
+        DirKind::Data,
+        DirKind::Config,
+        DirKind::Cache,
+        DirKind::Temporary,
+        DirKind::Log,
+        DirKind::State,
+    ];
+
+    /// A lowercase name, such as `"temporary"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DirKind::Home => "home",
+            DirKind::Config => "config",
+            DirKind::Data => "data",
+            DirKind::Cache => "cache",
+            DirKind::State => "state",
+            DirKind::Log => "log",
+            DirKind::Runtime => "runtime",
+            DirKind::Executables => "executables",
+            DirKind::Temporary => "temporary",
+        }
+    }
+}
+
+impl fmt::Display for DirKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown directory kind: '{0}'")]
+pub struct UnknownDirKind(pub String);
+
+impl FromStr for DirKind {
+    type Err = UnknownDirKind;
+
+    /// Parses the names returned by `as_str`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DirKind::ALL
+            .iter()
+            .copied()
+            .find(|x| x.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownDirKind(s.to_string()))
+    }
+}
+
+/// Iterates over the directories of an `AppDirs`; see `AppDirs::iter`.
+#[derive(Debug, Clone)]
+pub struct DirIter<'a> {
+    dirs: &'a crate::AppDirs,
+    kinds: std::slice::Iter<'static, DirKind>,
+}
+
+impl<'a> DirIter<'a> {
+    pub(crate) fn new(dirs: &'a crate::AppDirs) -> DirIter<'a> {
+        DirIter {
+            dirs,
+            kinds: DirKind::APP.iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for DirIter<'a> {
+    type Item = (DirKind, &'a Path);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let dirs = self.dirs;
+        self.kinds
+            .by_ref()
+            .find_map(|kind| dirs.dir(*kind).map(|path| (*kind, path)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.kinds.size_hint().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::AppDirs;
+
+    #[test]
+    fn iter() {
+        let data = PathBuf::from("/data");
+        let dirs = AppDirs::new(
+            data.clone(),
+            data.clone(),
+            data.clone(),
+            data.clone(),
+            data,
+            PathBuf::from("/tmp"),
+        );
+        let kinds = dirs.iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, DirKind::APP);
+        assert_eq!(dirs.dir(DirKind::Temporary), Some(Path::new("/tmp")));
+        assert_eq!(dirs.dir(DirKind::Home), None);
+    }
+
+    #[test]
+    fn parse() {
+        for kind in DirKind::ALL.iter() {
+            assert_eq!(kind.to_string().parse::<DirKind>(), Ok(*kind));
+        }
+        assert_eq!("Config".parse::<DirKind>(), Ok(DirKind::Config));
+        assert!("tmp".parse::<DirKind>().is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! Installation directories following the GNU coding standards, from which
+//! the system-wide directories of `pathos::linux::system` are derived.
+//!
+//! The defaults can be set when `pathos` is built, through the environment
+//! variables `PATHOS_PREFIX`, `PATHOS_EXEC_PREFIX`, `PATHOS_BINDIR`,
+//! `PATHOS_LIBDIR`, `PATHOS_LIBEXECDIR`, `PATHOS_DATADIR`,
+//! `PATHOS_SYSCONFDIR`, `PATHOS_LOCALSTATEDIR` and `PATHOS_RUNSTATEDIR`, and
+//! replaced at runtime with `linux::system::set_install_dirs`.
+//!
+//! Relocatable builds can instead find where they were installed from the
+//! running executable with `install_prefix` and `resources_dir`.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{AppDirs, Error};
+
+/// One of the GNU installation directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InstallDir {
+    Prefix,
+    ExecPrefix,
+    Bindir,
+    Libdir,
+    Libexecdir,
+    Datadir,
+    Sysconfdir,
+    Localstatedir,
+    Runstatedir,
+}
+
+/// Where a package is installed: a prefix, and optionally any directory in
+/// place of its default relative to the prefix.
+///
+/// The defaults are those of the GNU coding standards, adjusted for the
+/// prefixes the FHS treats specially:
+///
+/// | Prefix            | `sysconfdir`     | `localstatedir`  | `runstatedir`         |
+/// |-------------------|------------------|------------------|-----------------------|
+/// | `/usr`            | `/etc`           | `/var`           | `/run`                |
+/// | `/usr/local`      | `/usr/local/etc` | `/var/local`     | `/run`                |
+/// | `/opt/<package>`  | `/etc/opt/<package>` | `/var/opt/<package>` | `/run`        |
+/// | anything else     | `<prefix>/etc`   | `<prefix>/var`   | `<localstatedir>/run` |
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InstallDirs {
+    prefix: PathBuf,
+    known: BTreeMap<InstallDir, PathBuf>,
+}
+
+impl InstallDirs {
+    pub fn new<P: Into<PathBuf>>(prefix: P) -> InstallDirs {
+        InstallDirs {
+            prefix: prefix.into(),
+            known: BTreeMap::new(),
+        }
+    }
+
+    /// The directories `pathos` was built with: `/usr` unless overridden by
+    /// the `PATHOS_*` environment variables at build time.
+    pub fn compiled() -> InstallDirs {
+        let mut dirs = InstallDirs::new(option_env!("PATHOS_PREFIX").unwrap_or("/usr"));
+
+        let known = [
+            (InstallDir::ExecPrefix, option_env!("PATHOS_EXEC_PREFIX")),
+            (InstallDir::Bindir, option_env!("PATHOS_BINDIR")),
+            (InstallDir::Libdir, option_env!("PATHOS_LIBDIR")),
+            (InstallDir::Libexecdir, option_env!("PATHOS_LIBEXECDIR")),
+            (InstallDir::Datadir, option_env!("PATHOS_DATADIR")),
+            (InstallDir::Sysconfdir, option_env!("PATHOS_SYSCONFDIR")),
+            (
+                InstallDir::Localstatedir,
+                option_env!("PATHOS_LOCALSTATEDIR"),
+            ),
+            (InstallDir::Runstatedir, option_env!("PATHOS_RUNSTATEDIR")),
+        ];
+
+        for (dir, value) in known.iter() {
+            if let Some(value) = value {
+                dirs.set(*dir, value);
+            }
+        }
+
+        dirs
+    }
+
+    /// Sets the directory `dir`. Setting `Prefix` replaces the prefix, and
+    /// the defaults of any directories not set move with it.
+    pub fn set<P: Into<PathBuf>>(&mut self, dir: InstallDir, path: P) -> &mut Self {
+        match dir {
+            InstallDir::Prefix => self.prefix = path.into(),
+            dir => {
+                self.known.insert(dir, path.into());
+            }
+        }
+        self
+    }
+
+    /// The directory `dir`, whether set or defaulted.
+    pub fn get(&self, dir: InstallDir) -> PathBuf {
+        match dir {
+            InstallDir::Prefix => self.prefix.clone(),
+            InstallDir::ExecPrefix => self.exec_prefix(),
+            InstallDir::Bindir => self.bindir(),
+            InstallDir::Libdir => self.libdir(),
+            InstallDir::Libexecdir => self.libexecdir(),
+            InstallDir::Datadir => self.datadir(),
+            InstallDir::Sysconfdir => self.sysconfdir(),
+            InstallDir::Localstatedir => self.localstatedir(),
+            InstallDir::Runstatedir => self.runstatedir(),
+        }
+    }
+
+    #[inline]
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    pub fn exec_prefix(&self) -> PathBuf {
+        self.get_or(InstallDir::ExecPrefix, || self.prefix.clone())
+    }
+
+    pub fn bindir(&self) -> PathBuf {
+        self.get_or(InstallDir::Bindir, || self.exec_prefix().join("bin"))
+    }
+
+    pub fn libdir(&self) -> PathBuf {
+        self.get_or(InstallDir::Libdir, || self.exec_prefix().join("lib"))
+    }
+
+    pub fn libexecdir(&self) -> PathBuf {
+        self.get_or(InstallDir::Libexecdir, || {
+            self.exec_prefix().join("libexec")
+        })
+    }
+
+    pub fn datadir(&self) -> PathBuf {
+        self.get_or(InstallDir::Datadir, || self.prefix.join("share"))
+    }
+
+    pub fn sysconfdir(&self) -> PathBuf {
+        self.get_or(InstallDir::Sysconfdir, || match self.convention() {
+            Convention::System => PathBuf::from("/etc"),
+            Convention::Opt(package) => Path::new("/etc/opt").join(package),
+            Convention::Local | Convention::Other => self.prefix.join("etc"),
+        })
+    }
+
+    pub fn localstatedir(&self) -> PathBuf {
+        self.get_or(InstallDir::Localstatedir, || match self.convention() {
+            Convention::System => PathBuf::from("/var"),
+            Convention::Local => PathBuf::from("/var/local"),
+            Convention::Opt(package) => Path::new("/var/opt").join(package),
+            Convention::Other => self.prefix.join("var"),
+        })
+    }
+
+    pub fn runstatedir(&self) -> PathBuf {
+        self.get_or(InstallDir::Runstatedir, || match self.convention() {
+            Convention::Other => self.localstatedir().join("run"),
+            _ => PathBuf::from("/run"),
+        })
+    }
+
+    /// The system-wide directories of an app with the given prefix.
+    ///
+    /// Configuration goes in `sysconfdir`, and the cache, logs, state and
+    /// temporary files in `cache`, `log`, `lib` and `tmp` under
+    /// `localstatedir`.
+    pub fn app_layout<P: AsRef<Path>>(&self, prefix: P) -> AppDirs {
+        let prefix = prefix.as_ref();
+        let localstatedir = self.localstatedir();
+
+        AppDirs::new(
+            self.datadir().join(prefix),
+            self.sysconfdir().join(prefix),
+            localstatedir.join("cache").join(prefix),
+            localstatedir.join("log").join(prefix),
+            localstatedir.join("lib").join(prefix),
+            localstatedir.join("tmp").join(prefix),
+        )
+    }
+
+    fn get_or(&self, dir: InstallDir, default: impl FnOnce() -> PathBuf) -> PathBuf {
+        self.known.get(&dir).cloned().unwrap_or_else(default)
+    }
+
+    fn convention(&self) -> Convention<'_> {
+        if self.prefix == Path::new("/") || self.prefix == Path::new("/usr") {
+            Convention::System
+        } else if self.prefix == Path::new("/usr/local") {
+            Convention::Local
+        } else {
+            match self.prefix.strip_prefix("/opt") {
+                Ok(package) if package != Path::new("") => Convention::Opt(package),
+                _ => Convention::Other,
+            }
+        }
+    }
+}
+
+impl Default for InstallDirs {
+    fn default() -> Self {
+        InstallDirs::compiled()
+    }
+}
+
+enum Convention<'a> {
+    System,
+    Local,
+    Opt(&'a Path),
+    Other,
+}
+
+/// How an install prefix was found from the running executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Detection {
+    /// Inside an AppImage, whose `APPDIR` contains a `usr` prefix.
+    AppImage,
+    /// Inside a Cargo `target` directory, where resources are in the
+    /// workspace.
+    Cargo,
+    /// At `<prefix>/bin/<exe>`.
+    Bin,
+    /// None of the above: the executable's own directory, as in a flat
+    /// tarball.
+    Executable,
+}
+
+/// A directory found from the running executable, and how.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Detected {
+    path: PathBuf,
+    detection: Detection,
+}
+
+impl Detected {
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    pub fn detection(&self) -> Detection {
+        self.detection
+    }
+
+    #[inline]
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// Detects the install prefix of the executable at `exe`, which is
+    /// `current_exe` with symlinks resolved for `install_prefix`. `appdir` is
+    /// the AppImage's `APPDIR`, if any.
+    pub fn prefix_of(exe: &Path, appdir: Option<&Path>) -> Option<Detected> {
+        let dir = exe.parent()?;
+
+        if let Some(appdir) = appdir.filter(|x| exe.starts_with(x)) {
+            return Some(Detected::new(appdir.join("usr"), Detection::AppImage));
+        }
+
+        // `target/<profile>`, `target/<triple>/<profile>`, and `deps` or
+        // `examples` below either.
+        let cargo = dir
+            .ancestors()
+            .skip(1)
+            .take(3)
+            .find(|x| x.file_name() == Some("target".as_ref()));
+        if let Some(workspace) = cargo.and_then(Path::parent) {
+            return Some(Detected::new(workspace.to_path_buf(), Detection::Cargo));
+        }
+
+        match dir.file_name() {
+            Some(name) if name == "bin" || name == "sbin" => {
+                Some(Detected::new(dir.parent()?.to_path_buf(), Detection::Bin))
+            }
+            _ => Some(Detected::new(dir.to_path_buf(), Detection::Executable)),
+        }
+    }
+
+    /// Where the resources of the app with the given prefix are, relative to
+    /// an install prefix detected by `prefix_of`: `<prefix>/share/<app>`, or
+    /// the workspace or executable directory itself for `Cargo` and
+    /// `Executable`.
+    pub fn resources<P: AsRef<Path>>(&self, prefix: P) -> Detected {
+        let path = match self.detection {
+            Detection::AppImage | Detection::Bin => self.path.join("share").join(prefix),
+            Detection::Cargo | Detection::Executable => self.path.clone(),
+        };
+
+        Detected::new(path, self.detection)
+    }
+
+    fn new(path: PathBuf, detection: Detection) -> Detected {
+        Detected { path, detection }
+    }
+}
+
+/// The install prefix of the running executable, e.g. `/opt/app` for
+/// `/opt/app/bin/app`.
+pub fn install_prefix() -> Result<Detected, Error> {
+    let exe = std::env::current_exe()
+        .map_err(|_| Error::DirectoryResolutionFailed("Current executable"))?;
+    let exe = std::fs::canonicalize(&exe).unwrap_or(exe);
+    let appdir = std::env::var_os("APPDIR").map(PathBuf::from);
+
+    Detected::prefix_of(&exe, appdir.as_deref()).ok_or(Error::NotFound("Install prefix"))
+}
+
+/// The resources bundled with the running executable for the app with the
+/// given prefix, e.g. `/opt/app/share/app` for `/opt/app/bin/app`.
+pub fn resources_dir<P: AsRef<Path>>(prefix: P) -> Result<Detected, Error> {
+    install_prefix().map(|x| x.resources(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conventions() {
+        let usr = InstallDirs::new("/usr");
+        assert_eq!(usr.sysconfdir(), Path::new("/etc"));
+        assert_eq!(usr.libexecdir(), Path::new("/usr/libexec"));
+        assert_eq!(usr.runstatedir(), Path::new("/run"));
+
+        let local = InstallDirs::new("/usr/local");
+        assert_eq!(local.sysconfdir(), Path::new("/usr/local/etc"));
+        assert_eq!(local.localstatedir(), Path::new("/var/local"));
+
+        let opt = InstallDirs::new("/opt/vendor/app");
+        assert_eq!(opt.sysconfdir(), Path::new("/etc/opt/vendor/app"));
+        assert_eq!(opt.localstatedir(), Path::new("/var/opt/vendor/app"));
+        assert_eq!(opt.datadir(), Path::new("/opt/vendor/app/share"));
+
+        let mut home = InstallDirs::new("/home/x/.local");
+        home.set(InstallDir::ExecPrefix, "/home/x/.local/x86_64");
+        assert_eq!(home.runstatedir(), Path::new("/home/x/.local/var/run"));
+        assert_eq!(home.bindir(), Path::new("/home/x/.local/x86_64/bin"));
+        assert_eq!(home.datadir(), Path::new("/home/x/.local/share"));
+    }
+
+    #[test]
+    fn app_layout() {
+        let dirs = InstallDirs::new("/usr").app_layout("app");
+        assert_eq!(dirs.data_dir(), Path::new("/usr/share/app"));
+        assert_eq!(dirs.config_dir(), Path::new("/etc/app"));
+        assert_eq!(dirs.state_dir(), Path::new("/var/lib/app"));
+        assert_eq!(dirs.temporary_dir(), Path::new("/var/tmp/app"));
+
+        let dirs = InstallDirs::new("/opt/app").app_layout("app");
+        assert_eq!(dirs.config_dir(), Path::new("/etc/opt/app/app"));
+        assert_eq!(dirs.log_dir(), Path::new("/var/opt/app/log/app"));
+    }
+
+    #[test]
+    fn detection() {
+        let detect = |exe: &str, appdir: Option<&str>| {
+            let found = Detected::prefix_of(Path::new(exe), appdir.map(Path::new)).unwrap();
+            (found.path().to_path_buf(), found.detection())
+        };
+
+        assert_eq!(
+            detect("/opt/app/bin/app", None),
+            (PathBuf::from("/opt/app"), Detection::Bin)
+        );
+        assert_eq!(
+            detect("/tmp/.mount_x/usr/bin/app", Some("/tmp/.mount_x")),
+            (PathBuf::from("/tmp/.mount_x/usr"), Detection::AppImage)
+        );
+        assert_eq!(
+            detect(
+                "/src/app/target/x86_64-unknown-linux-gnu/debug/deps/app-1f",
+                None
+            ),
+            (PathBuf::from("/src/app"), Detection::Cargo)
+        );
+        assert_eq!(
+            detect("/home/x/app-1.0/app", None),
+            (PathBuf::from("/home/x/app-1.0"), Detection::Executable)
+        );
+
+        let bin = Detected::prefix_of(Path::new("/home/x/.local/bin/app"), None).unwrap();
+        assert_eq!(
+            bin.resources("app").path(),
+            Path::new("/home/x/.local/share/app")
+        );
+    }
+}
@@ -1,2 +1,6 @@
+//! Directories on iOS. Apart from `user::iri`, which only builds `container:`
+//! IRIs, the native functions are only available when compiling for iOS.
+
+#[cfg(target_os = "ios")]
 pub mod system;
 pub mod user;
@@ -1,60 +1,173 @@
 // Re-export all macOS items as iOS items.
+#[cfg(target_os = "ios")]
 pub use crate::macos::user::*;
 
+#[cfg(target_os = "ios")]
+pub(crate) use crate::macos::user::provenance;
+
 pub mod iri {
-    use crate::Error;
+    use crate::{iri::IriPathBuilder, Error};
     use iref::IriBuf;
-    use std::convert::TryInto;
+    use std::path::Path;
 
     #[inline]
-    pub fn home_dir() -> IriBuf {
-        IriBuf::new("container:/").unwrap()
+    pub fn home_dir() -> Result<IriBuf, Error> {
+        Ok(IriPathBuilder::container().build()?)
     }
 
     #[inline]
-    pub(super) fn library_dir<P: AsRef<str>>(x: &str, prefix: P) -> Result<IriBuf, Error> {
-        let mut iri = IriBuf::new("container:/Library/").unwrap();
+    pub(super) fn library_dir<P: AsRef<Path>>(
+        x: &str,
+        prefix: P,
+        extra: &[&str],
+    ) -> Result<IriBuf, Error> {
+        let mut builder = IriPathBuilder::container();
+        builder.push("Library")?.push_all(x)?.push_path(prefix)?;
 
-        for item in x.split("/") {
-            iri.path_mut().push(item.try_into().unwrap());
+        for item in extra {
+            builder.push(item)?;
         }
 
-        for item in prefix.as_ref().split("/") {
-            iri.path_mut().push(item.try_into().unwrap());
-        }
+        Ok(builder.directory().build()?)
+    }
+
+    #[inline]
+    pub fn application_support_dir() -> Result<IriBuf, Error> {
+        library_dir("Application Support", "", &[])
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        application_support_dir()
+    }
+
+    #[inline]
+    pub fn preferences_dir() -> Result<IriBuf, Error> {
+        library_dir("Preferences", "", &[])
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        library_dir("Caches", "", &[])
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        library_dir("Logs", "", &[])
+    }
+
+    #[inline]
+    pub fn services_dir() -> Result<IriBuf, Error> {
+        library_dir("Services", "", &[])
+    }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        preferences_dir()
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        application_support_dir()
+    }
+
+    #[inline]
+    pub fn runtime_dir() -> Result<IriBuf, Error> {
+        temporary_dir()
+    }
+
+    #[inline]
+    pub fn executables_dir() -> Result<IriBuf, Error> {
+        Err(Error::NotFound("Executables"))
+    }
+
+    /// `NSTemporaryDirectory()`, the `tmp` directory of the container.
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        Ok(IriPathBuilder::container()
+            .push("tmp")?
+            .directory()
+            .build()?)
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        library_dir("Application Support", prefix, &[])
+    }
 
-        iri.path_mut().open();
-        Ok(iri)
+    #[inline]
+    pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        library_dir("Application Support", prefix, &["config"])
     }
 
     #[inline]
-    pub fn app_config_dir<P: AsRef<str>>(prefix: P) -> Result<IriBuf, Error> {
-        library_dir("Preferences", prefix)
+    pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        library_dir("Caches", prefix, &[])
     }
 
     #[inline]
-    pub fn app_cache_dir<P: AsRef<str>>(prefix: P) -> Result<IriBuf, Error> {
-        library_dir("Caches", prefix)
+    pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        library_dir("Logs", prefix, &[])
     }
 
     #[inline]
-    pub fn app_log_dir<P: AsRef<str>>(prefix: P) -> Result<IriBuf, Error> {
-        library_dir("Logs", prefix)
+    pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        library_dir("Application Support", prefix, &["state"])
     }
 
     #[inline]
-    pub fn app_temporary_dir<P: AsRef<str>>(prefix: P) -> Result<IriBuf, Error> {
-        let mut path = app_cache_dir(prefix)?;
-        path.path_mut().push("tmp".try_into().unwrap());
-        path.path_mut().open();
-        Ok(path)
+    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        library_dir("Caches", prefix, &["tmp"])
     }
 }
 
-#[cfg(all(target_os = "ios", test))]
+#[cfg(test)]
 mod tests {
     #[test]
     fn smoke_test() {
         println!("{:?}", super::iri::app_temporary_dir("meow").unwrap());
     }
+
+    #[test]
+    fn awkward_prefixes() {
+        assert_eq!(
+            super::iri::app_cache_dir("Special Company?/#1")
+                .unwrap()
+                .as_str(),
+            "container:/Library/Caches/Special%20Company%3F/%231/"
+        );
+        assert!(super::iri::app_cache_dir("../Preferences").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn iris_match_layout() {
+        use super::iri;
+        use crate::{iri::SchemeRegistry, layout::Roots, DirKind, Platform};
+        use iref::IriBuf;
+        use std::path::Path;
+
+        let root = Path::new("/container");
+        let user = Platform::Ios.user_layout(&Roots::new(root));
+        let dirs = Platform::Ios.app_layout(&user, "app");
+
+        let mut registry = SchemeRegistry::new();
+        registry.register("container", root).unwrap();
+
+        type AppIri = fn(&'static str) -> Result<IriBuf, crate::Error>;
+
+        let iris: [(DirKind, AppIri); 6] = [
+            (DirKind::Data, iri::app_data_dir),
+            (DirKind::Config, iri::app_config_dir),
+            (DirKind::Cache, iri::app_cache_dir),
+            (DirKind::Log, iri::app_log_dir),
+            (DirKind::State, iri::app_state_dir),
+            (DirKind::Temporary, iri::app_temporary_dir),
+        ];
+
+        for (kind, iri) in iris.iter() {
+            let path = registry.resolve(&iri("app").unwrap()).unwrap();
+            assert_eq!(Some(&*path), dirs.dir(*kind), "{}", kind);
+        }
+    }
 }
@@ -0,0 +1,228 @@
+use std::{
+    ffi::OsStr,
+    path::{Component, Path},
+};
+
+use iref::IriBuf;
+use os_str_bytes::OsStrBytes;
+use percent_encoding::{percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use super::{scheme::is_valid_scheme, Error};
+
+/// Everything but the characters RFC 3987 allows unescaped in a path segment
+/// (`iunreserved`, `sub-delims`, `:` and `@`). Non-ASCII is always escaped.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
+
+/// As `SEGMENT`, but `:` and `@` are delimiters in an authority.
+const HOST: &AsciiSet = &SEGMENT.add(b':').add(b'@');
+
+/// Builds path IRIs such as `container:/Library/Caches/` or `file:///C:/x`
+/// one segment at a time, percent-encoding each segment.
+///
+/// Segments that could not survive a round trip through
+/// `IriBufExt::to_path_buf` are rejected rather than encoded: empty and dot
+/// segments, and segments containing `/` or NUL.
+#[derive(Debug, Clone)]
+pub struct IriPathBuilder {
+    scheme: String,
+    authority: Option<String>,
+    segments: Vec<String>,
+    directory: bool,
+}
+
+impl IriPathBuilder {
+    pub fn new(scheme: &str) -> Result<IriPathBuilder, Error> {
+        if !is_valid_scheme(scheme) {
+            return Err(Error::InvalidSchemeName(scheme.to_string()));
+        }
+
+        Ok(IriPathBuilder {
+            scheme: scheme.to_ascii_lowercase(),
+            authority: None,
+            segments: vec![],
+            directory: false,
+        })
+    }
+
+    /// A builder for `container:` IRIs.
+    pub fn container() -> IriPathBuilder {
+        IriPathBuilder::new("container").unwrap()
+    }
+
+    /// A builder for `file:` IRIs on the local host, in the canonical
+    /// `file:///` form.
+    pub fn file() -> IriPathBuilder {
+        let mut builder = IriPathBuilder::new("file").unwrap();
+        builder.authority = Some(String::new());
+        builder
+    }
+
+    /// Sets the host of a `file:` IRI, for UNC paths.
+    pub(crate) fn host(&mut self, host: &[u8]) -> Result<&mut IriPathBuilder, Error> {
+        check_segment(host)?;
+        self.authority = Some(percent_encode(host, HOST).to_string());
+        Ok(self)
+    }
+
+    /// Appends a single segment.
+    pub fn push<S: AsRef<str>>(&mut self, segment: S) -> Result<&mut IriPathBuilder, Error> {
+        self.push_bytes(segment.as_ref().as_bytes())
+    }
+
+    /// Appends every `/`-separated segment of `path`. Nothing is appended if
+    /// any segment is invalid.
+    pub fn push_all<S: AsRef<str>>(&mut self, path: S) -> Result<&mut IriPathBuilder, Error> {
+        let segments = path
+            .as_ref()
+            .split('/')
+            .map(|x| encode_segment(x.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.segments.extend(segments);
+        Ok(self)
+    }
+
+    /// Appends a single segment from an OS string, such as a file name.
+    pub fn push_os_str<S: AsRef<OsStr>>(
+        &mut self,
+        segment: S,
+    ) -> Result<&mut IriPathBuilder, Error> {
+        self.push_bytes(&segment.as_ref().to_bytes())
+    }
+
+    /// Appends every component of a relative path. Roots, prefixes and dot
+    /// components are rejected, and nothing is appended if any component is
+    /// invalid.
+    pub fn push_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut IriPathBuilder, Error> {
+        let segments = path
+            .as_ref()
+            .components()
+            .map(|component| match component {
+                Component::Normal(value) => encode_segment(&value.to_bytes()),
+                _ => Err(Error::InvalidComponent),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.segments.extend(segments);
+        Ok(self)
+    }
+
+    /// Marks the IRI as referring to a directory, giving it a trailing `/`.
+    pub fn directory(&mut self) -> &mut IriPathBuilder {
+        self.directory = true;
+        self
+    }
+
+    pub fn build(&self) -> Result<IriBuf, Error> {
+        let authority = match &self.authority {
+            Some(authority) => format!("//{}", authority),
+            None => String::new(),
+        };
+
+        let trailing = if self.directory && !self.segments.is_empty() {
+            "/"
+        } else {
+            ""
+        };
+
+        let input = format!(
+            "{}:{}/{}{}",
+            self.scheme,
+            authority,
+            self.segments.join("/"),
+            trailing
+        );
+
+        IriBuf::new(&input).map_err(Error::InvalidIri)
+    }
+
+    fn push_bytes(&mut self, segment: &[u8]) -> Result<&mut IriPathBuilder, Error> {
+        self.segments.push(encode_segment(segment)?);
+        Ok(self)
+    }
+}
+
+#[inline]
+fn encode_segment(segment: &[u8]) -> Result<String, Error> {
+    check_segment(segment)?;
+    Ok(percent_encode(segment, SEGMENT).to_string())
+}
+
+#[inline]
+fn check_segment(segment: &[u8]) -> Result<(), Error> {
+    if segment.is_empty() {
+        return Err(Error::EmptySegment);
+    }
+
+    if segment == b"." || segment == b".." {
+        return Err(Error::InvalidComponent);
+    }
+
+    if segment.contains(&0) {
+        return Err(Error::NulByte);
+    }
+
+    if segment.contains(&b'/') {
+        return Err(Error::EncodedSeparator);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let iri = IriPathBuilder::container()
+            .push("Library")
+            .unwrap()
+            .push_all("Caches/Special Company?/#1")
+            .unwrap()
+            .directory()
+            .build()
+            .unwrap();
+        assert_eq!(
+            iri.as_str(),
+            "container:/Library/Caches/Special%20Company%3F/%231/"
+        );
+
+        let iri = IriPathBuilder::file().push("C:").unwrap().build().unwrap();
+        assert_eq!(iri.as_str(), "file:///C:");
+
+        let iri = IriPathBuilder::container().directory().build().unwrap();
+        assert_eq!(iri.as_str(), "container:/");
+    }
+
+    #[test]
+    fn invalid_segments() {
+        let mut builder = IriPathBuilder::container();
+        assert!(matches!(builder.push(""), Err(Error::EmptySegment)));
+        assert!(matches!(builder.push_all("a//b"), Err(Error::EmptySegment)));
+        assert_eq!(builder.build().unwrap().as_str(), "container:/");
+        assert!(matches!(builder.push(".."), Err(Error::InvalidComponent)));
+        assert!(matches!(builder.push("a/b"), Err(Error::EncodedSeparator)));
+        assert!(matches!(builder.push("a\0b"), Err(Error::NulByte)));
+        assert!(matches!(
+            builder.push_path("/etc"),
+            Err(Error::InvalidComponent)
+        ));
+        assert!(IriPathBuilder::new("not a scheme").is_err());
+    }
+}
@@ -4,10 +4,22 @@ use iref::IriBuf;
 use os_str_bytes::OsStringBytes;
 use percent_encoding::percent_decode_str;
 
-use crate::path::absolute::AbsolutePathBuf;
+use crate::path::absolute::{AbsolutePath, AbsolutePathBuf};
+use crate::path::foreign::{PosixPath, WindowsPath};
+use crate::Platform;
+
+mod builder;
+pub mod scheme;
+
+pub use builder::IriPathBuilder;
+pub use scheme::SchemeRegistry;
 
 pub trait IriBufExt {
     fn to_path_buf(&self) -> Result<PathBuf, Error>;
+
+    /// Converts a `file:` IRI into a path string as the given platform would
+    /// write it, regardless of the host operating system.
+    fn to_path_string_for(&self, platform: Platform) -> Result<String, Error>;
 }
 
 #[cfg(not(any(target_os = "android", target_os = "ios",)))]
@@ -15,34 +27,64 @@ impl IriBufExt for IriBuf {
     fn to_path_buf(&self) -> Result<PathBuf, Error> {
         Ok(resolve_file_iri(self)?.to_path_buf())
     }
+
+    fn to_path_string_for(&self, platform: Platform) -> Result<String, Error> {
+        path_string_for(self, platform)
+    }
 }
 
+#[cfg(target_os = "android")]
+const SUPPORTED_SCHEMES: &[&str] = &["file", "container", "external"];
+
+#[cfg(target_os = "ios")]
+const SUPPORTED_SCHEMES: &[&str] = &["file", "container"];
+
 #[cfg(any(target_os = "android", target_os = "ios",))]
 impl IriBufExt for IriBuf {
     fn to_path_buf(&self) -> Result<PathBuf, Error> {
-        match self.scheme().as_str() {
+        // Schemes are case-insensitive (RFC 3986, section 3.1).
+        match &*self.scheme().as_str().to_ascii_lowercase() {
             "file" => Ok(resolve_file_iri(self)?.to_path_buf()),
             "container" => Ok(resolve_container_iri(container_path()?, self)?.to_path_buf()),
+            #[cfg(target_os = "android")]
+            "external" => Ok(resolve_container_iri(external_path()?, self)?.to_path_buf()),
             unhandled => Err(Error::InvalidScheme(
                 unhandled.to_string(),
-                &["file", "container"],
+                SUPPORTED_SCHEMES,
             )),
         }
     }
+
+    fn to_path_string_for(&self, platform: Platform) -> Result<String, Error> {
+        path_string_for(self, platform)
+    }
+}
+
+#[inline]
+fn path_string_for(iri: &IriBuf, platform: Platform) -> Result<String, Error> {
+    if platform.is_windows() {
+        Ok(WindowsPath::from_file_iri(iri)?.to_string())
+    } else {
+        Ok(PosixPath::from_file_iri(iri)?.to_string())
+    }
+}
+
+#[cfg(target_os = "android")]
+#[inline(always)]
+pub(crate) fn container_path() -> Result<PathBuf, Error> {
+    crate::android::user::container_path().map_err(|e| Error::UnresolvableContainer(e.to_string()))
 }
 
 #[cfg(target_os = "android")]
 #[inline(always)]
-fn container_path() -> Result<PathBuf, Error> {
-    let p = crate::android::user::CONTAINER_PATH.get().ok_or_else(|| {
-        Error::UnresolvableContainer("No path set for container; call `set_container_path`.".into())
-    })?;
-    Ok(p.to_path_buf())
+pub(crate) fn external_path() -> Result<PathBuf, Error> {
+    crate::android::external::external_app_dir()
+        .map_err(|e| Error::UnresolvableContainer(e.to_string()))
 }
 
 #[cfg(target_os = "ios")]
 #[inline(always)]
-fn container_path() -> Result<PathBuf, Error> {
+pub(crate) fn container_path() -> Result<PathBuf, Error> {
     Ok(crate::ios::user::home_dir()
         .map_err(|_| Error::UnresolvableContainer("Could not resolve home directory".into()))?
         .to_path_buf())
@@ -73,33 +115,135 @@ pub enum Error {
 
     #[error("Could not resolve container to path: {0}")]
     UnresolvableContainer(String),
+
+    #[error("IRI path segment contains an encoded path separator.")]
+    EncodedSeparator,
+
+    #[error("IRI path segment contains an encoded NUL byte.")]
+    NulByte,
+
+    #[error("IRI path segment could not be converted to an OS string.")]
+    InvalidEncoding,
+
+    #[error("IRI resolves to a path outside of its container.")]
+    OutsideContainer,
+
+    #[error("IRI refers to a remote host that cannot be represented locally: '{0}'")]
+    RemoteHost(String),
+
+    #[error("Path IRIs must not have a query component.")]
+    UnexpectedQuery,
+
+    #[error("Path IRIs must not have a fragment component.")]
+    UnexpectedFragment,
+
+    #[error("Invalid scheme name: '{0}'")]
+    InvalidSchemeName(String),
+
+    #[error("No root registered for scheme: '{0}'")]
+    UnknownScheme(String),
+
+    #[error("IRI path segments must not be empty.")]
+    EmptySegment,
+
+    #[error("Invalid foreign path: {0}")]
+    InvalidForeignPath(#[from] crate::path::foreign::ParseError),
 }
 
-#[inline]
-fn resolve_file_iri(iri: &IriBuf) -> Result<AbsolutePathBuf, Error> {
-    if iri.scheme() != "file" {
+/// Percent-decodes a single IRI path segment into an OS string for the host.
+fn decode_segment(segment: &str) -> Result<OsString, Error> {
+    let bytes = decode_segment_bytes(segment, cfg!(windows))?;
+    OsString::from_bytes(bytes).map_err(|_| Error::InvalidEncoding)
+}
+
+/// Percent-decodes a single IRI path segment into a string for the given platform.
+pub(crate) fn decode_segment_for(segment: &str, platform: Platform) -> Result<String, Error> {
+    let bytes = decode_segment_bytes(segment, platform.is_windows())?;
+    Ok(String::from_utf8(bytes.into_owned())?)
+}
+
+/// Percent-decodes a single IRI path segment.
+///
+/// Anything that would change the shape of the path once the segments are
+/// joined back together is rejected: dot segments (before or after decoding),
+/// encoded separators and NUL bytes.
+fn decode_segment_bytes(segment: &str, windows: bool) -> Result<Cow<'_, [u8]>, Error> {
+    if is_dot_segment(segment.as_bytes()) {
+        return Err(Error::InvalidComponent);
+    }
+
+    let bytes: Cow<'_, [u8]> = percent_decode_str(segment).into();
+
+    if is_dot_segment(&bytes) {
+        return Err(Error::InvalidComponent);
+    }
+
+    if bytes.contains(&0) {
+        return Err(Error::NulByte);
+    }
+
+    if bytes.iter().any(|b| *b == b'/' || (windows && *b == b'\\')) {
+        return Err(Error::EncodedSeparator);
+    }
+
+    Ok(bytes)
+}
+
+#[inline(always)]
+fn is_dot_segment(bytes: &[u8]) -> bool {
+    bytes == b"." || bytes == b".."
+}
+
+/// Checks that an IRI is a `file:` IRI with no query or fragment.
+pub(crate) fn check_file_iri(iri: &IriBuf) -> Result<(), Error> {
+    if !iri.scheme().as_str().eq_ignore_ascii_case("file") {
         return Err(crate::iri::Error::InvalidScheme(
             iri.scheme().to_string(),
             &["file"],
         ));
     }
 
+    if iri.query().is_some() {
+        return Err(Error::UnexpectedQuery);
+    }
+
+    if iri.fragment().is_some() {
+        return Err(Error::UnexpectedFragment);
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn resolve_file_iri(iri: &IriBuf) -> Result<AbsolutePathBuf, Error> {
+    check_file_iri(iri)?;
+
+    let host = file_iri_host(iri, cfg!(windows))?;
+
     if iri.path().first().is_some() {
-        let mut segments = iri.path().into_iter().map(|segment| -> OsString {
-            let bytes: Cow<'_, [u8]> = percent_decode_str(segment.as_str()).into();
-            // This should never panic, and according to the documentation,
-            // panicking here is the correct behaviour if it _does_ break an invariant and fail.
-            OsString::from_bytes(bytes)
-                .expect("Invariant failed to be upheld: invalid OS string data")
-        });
+        let mut segments = iri
+            .path()
+            .into_iter()
+            .map(|segment| decode_segment(segment.as_str()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+
+        let sep = OsString::from(std::path::MAIN_SEPARATOR.to_string());
 
         let mut start = OsString::new();
-        if !cfg!(unix) {
-            start.push(segments.next().unwrap())
+        match host {
+            // A remote host on Windows is a UNC path: `\\host\share\...`
+            Some(host) => {
+                start.push(&sep);
+                start.push(&sep);
+                start.push(host);
+                start.push(&sep);
+                start.push(segments.next().unwrap());
+            }
+            None if !cfg!(unix) => start.push(segments.next().unwrap()),
+            None => {}
         }
 
-        let sep = OsString::from(std::path::MAIN_SEPARATOR.to_string());
-
         let os_string = segments.fold(start, |mut acc: OsString, cur: OsString| {
             acc.push(&sep);
             acc.push(cur);
@@ -116,20 +260,56 @@ fn resolve_file_iri(iri: &IriBuf) -> Result<AbsolutePathBuf, Error> {
     }
 }
 
+/// Returns the remote host of a `file:` IRI, or `None` if it refers to the
+/// local machine (an empty or `localhost` authority).
+///
+/// Remote hosts can only be represented on Windows, as UNC paths.
+pub(crate) fn file_iri_host(iri: &IriBuf, windows: bool) -> Result<Option<String>, Error> {
+    let authority = match iri.authority() {
+        Some(authority) => authority,
+        None => return Ok(None),
+    };
+
+    let host = authority.host();
+    if authority.userinfo().is_none()
+        && authority.port().is_none()
+        && (host.is_empty() || host.as_str().eq_ignore_ascii_case("localhost"))
+    {
+        return Ok(None);
+    }
+
+    if windows && authority.userinfo().is_none() && authority.port().is_none() {
+        let host = decode_segment_for(host.as_str(), Platform::Windows)?;
+        return Ok(Some(host));
+    }
+
+    Err(Error::RemoteHost(authority.as_str().to_string()))
+}
+
 #[inline]
-#[cfg(any(target_os = "android", target_os = "ios"))]
 fn resolve_container_iri(prefix: PathBuf, iri: &IriBuf) -> Result<AbsolutePathBuf, Error> {
-    let segments = iri.path().into_iter().map(|segment| -> OsString {
-        let bytes: Cow<'_, [u8]> = percent_decode_str(segment.as_str()).into();
-        // This should never panic, and according to the documentation,
-        // panicking here is the correct behaviour if it _does_ break an invariant and fail.
-        OsString::from_bytes(bytes).expect("Invariant failed to be upheld: invalid OS string data")
-    });
+    if let Some(authority) = iri.authority().filter(|x| !x.is_empty()) {
+        return Err(Error::RemoteHost(authority.as_str().to_string()));
+    }
+
+    if iri.query().is_some() {
+        return Err(Error::UnexpectedQuery);
+    }
+
+    if iri.fragment().is_some() {
+        return Err(Error::UnexpectedFragment);
+    }
+
+    let segments = iri
+        .path()
+        .into_iter()
+        .map(|segment| decode_segment(segment.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
 
     let sep = OsString::from(std::path::MAIN_SEPARATOR.to_string());
 
-    let os_string = segments.fold(
-        prefix.into_os_string(),
+    let os_string = segments.into_iter().fold(
+        prefix.clone().into_os_string(),
         |mut acc: OsString, cur: OsString| {
             acc.push(&sep);
             acc.push(cur);
@@ -137,22 +317,57 @@ fn resolve_container_iri(prefix: PathBuf, iri: &IriBuf) -> Result<AbsolutePathBu
         },
     );
 
-    // let os_string = segments
-    //     .fold(prefix, |mut acc: PathBuf, cur: OsString| {
-    //         acc.push(cur);
-    //         acc
-    //     });
+    let path = std::path::Path::new(&os_string);
 
-    if !std::path::Path::new(&os_string).is_absolute() {
+    if !path.is_absolute() {
         return Err(Error::NotAbsolute);
     }
 
+    // Decoding already rejects anything that could walk out of the container,
+    // but the container must never be escaped, so check anyway.
+    let has_rel_components = path.components().any(|x| {
+        matches!(
+            x,
+            std::path::Component::CurDir | std::path::Component::ParentDir
+        )
+    });
+
+    if has_rel_components || !path.starts_with(&prefix) {
+        return Err(Error::OutsideContainer);
+    }
+
     Ok(AbsolutePathBuf(os_string))
 }
 
+/// Builds a `<scheme>:/...` IRI for a path relative to `root`, the inverse
+/// of `resolve_container_iri`.
+pub(crate) fn relative_iri(
+    scheme: &str,
+    root: &std::path::Path,
+    path: &AbsolutePath,
+) -> Result<IriBuf, Error> {
+    let rest = path
+        .strip_prefix(root)
+        .map_err(|_| Error::OutsideContainer)?;
+
+    IriPathBuilder::new(scheme)?.push_path(rest)?.build()
+}
+
+/// The `file:` IRI of a resolved directory, as returned by the `iri`
+/// submodules of each platform.
+#[inline]
+pub(crate) fn dir_iri<P: AsRef<std::path::Path>>(path: P) -> Result<IriBuf, crate::Error> {
+    use crate::path::absolute::AbsolutePathExt;
+
+    Ok(path.as_ref().to_absolute_path()?.to_file_iri()?)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{iri::IriBufExt, path::absolute::AbsolutePathBufExt};
+    use crate::{
+        iri::{Error, IriBufExt},
+        path::absolute::AbsolutePathBufExt,
+    };
     use iref::IriBuf;
     use std::path::PathBuf;
 
@@ -194,4 +409,131 @@ mod tests {
 
         println!("{:?}", path);
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn file_iri_strict_decoding() {
+        for input in &[
+            "file:///etc/a%2Fb",
+            "file:///etc/a%00b",
+            "file:///etc/../passwd",
+            "file:///etc/%2E%2E/passwd",
+            "file:///etc/%2e/passwd",
+        ] {
+            let iri = IriBuf::new(input).unwrap();
+            assert!(iri.to_path_buf().is_err(), "{} should be rejected", input);
+        }
+
+        let iri = IriBuf::new("file:///etc/a%20b").unwrap();
+        assert_eq!(iri.to_path_buf().unwrap(), PathBuf::from("/etc/a b"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn file_iri_authority() {
+        for input in &["file:///etc/hosts", "file://localhost/etc/hosts"] {
+            let iri = IriBuf::new(input).unwrap();
+            assert_eq!(iri.to_path_buf().unwrap(), PathBuf::from("/etc/hosts"));
+        }
+
+        let iri = IriBuf::new("file://otherhost/etc/passwd").unwrap();
+        match iri.to_path_buf() {
+            Err(Error::RemoteHost(host)) => assert_eq!(host, "otherhost"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn file_iri_authority() {
+        let iri = IriBuf::new("file://server/share/dir/file.txt").unwrap();
+        assert_eq!(
+            iri.to_path_buf().unwrap(),
+            PathBuf::from(r"\\server\share\dir\file.txt")
+        );
+
+        let path = PathBuf::from(r"\\server\share\dir\file.txt");
+        let iri = path.to_absolute_path_buf().unwrap().to_file_iri().unwrap();
+        assert_eq!(iri.as_str(), "file://server/share/dir/file.txt");
+    }
+
+    #[test]
+    fn file_iri_query_fragment() {
+        let iri = IriBuf::new("file:///a?b").unwrap();
+        assert!(matches!(iri.to_path_buf(), Err(Error::UnexpectedQuery)));
+
+        let iri = IriBuf::new("file:///a#c").unwrap();
+        assert!(matches!(iri.to_path_buf(), Err(Error::UnexpectedFragment)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn file_iri_canonical_form() {
+        let iri = PathBuf::from("/Library/Caches/Pahkat")
+            .to_absolute_path_buf()
+            .unwrap()
+            .to_file_iri()
+            .unwrap();
+        assert_eq!(iri.as_str(), "file:///Library/Caches/Pahkat");
+
+        let iri = PathBuf::from("/")
+            .to_absolute_path_buf()
+            .unwrap()
+            .to_file_iri()
+            .unwrap();
+        assert_eq!(iri.as_str(), "file:///");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn container_iri_from_path() {
+        use crate::path::absolute::AbsolutePathExt;
+
+        let prefix = PathBuf::from("/data/user/0/com.example");
+        let path = PathBuf::from("/data/user/0/com.example/files/a b.txt");
+
+        let iri =
+            super::relative_iri("container", &prefix, path.to_absolute_path().unwrap()).unwrap();
+        assert_eq!(iri.as_str(), "container:/files/a%20b.txt");
+        assert_eq!(
+            super::resolve_container_iri(prefix.clone(), &iri)
+                .unwrap()
+                .to_path_buf(),
+            path
+        );
+
+        let outside = PathBuf::from("/data/user/0/com.other/files");
+        assert!(matches!(
+            super::relative_iri("container", &prefix, outside.to_absolute_path().unwrap()),
+            Err(Error::OutsideContainer)
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn container_iri_containment() {
+        let prefix = PathBuf::from("/data/user/0/com.example");
+
+        let iri = IriBuf::new("container:/files/a%20b").unwrap();
+        assert_eq!(
+            super::resolve_container_iri(prefix.clone(), &iri)
+                .unwrap()
+                .to_path_buf(),
+            prefix.join("files/a b")
+        );
+
+        for input in &[
+            "container:/../../../../etc/passwd",
+            "container:/files/%2E%2E/%2E%2E/x",
+            "container:/files/..%2F..%2Fx",
+            "container:/files/x%00",
+        ] {
+            let iri = IriBuf::new(input).unwrap();
+            assert!(
+                super::resolve_container_iri(prefix.clone(), &iri).is_err(),
+                "{} should be rejected",
+                input
+            );
+        }
+    }
 }
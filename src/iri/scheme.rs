@@ -0,0 +1,214 @@
+//! Custom path IRI schemes, such as `app-data:/models/x.bin`.
+//!
+//! A [`SchemeRegistry`] maps scheme names to root directories, so that
+//! configuration can refer to files symbolically and still resolve on any
+//! machine, the same way `container:` IRIs do on iOS and Android.
+
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+};
+
+use iref::IriBuf;
+
+use crate::path::absolute::AbsolutePath;
+
+use super::{relative_iri, resolve_container_iri, resolve_file_iri, Error};
+
+#[derive(Debug, Clone, Default)]
+pub struct SchemeRegistry {
+    schemes: Vec<(String, PathBuf)>,
+}
+
+impl SchemeRegistry {
+    /// Creates an empty registry, which only understands `file:` IRIs.
+    pub fn new() -> SchemeRegistry {
+        SchemeRegistry::default()
+    }
+
+    /// Creates a registry with the default schemes for the given app prefix:
+    ///
+    /// - `app-data:`, `app-config:`, `app-cache:`, `app-log:`, `app-state:`
+    ///   and `app-temp:` for the directories from `pathos::user::app_dirs`
+    /// - `home:` for the user's home directory
+    /// - `container:` on iOS and Android, and `external:` on Android
+    #[cfg(any(unix, windows, target_os = "wasi"))]
+    pub fn with_app_dirs<P>(prefix: P) -> Result<SchemeRegistry, crate::Error>
+    where
+        P: Into<PathBuf>,
+    {
+        use crate::{DirKind, UserDirs as _};
+
+        let app_dirs = crate::user::app_dirs(prefix)?;
+        let user_dirs = crate::user::Dirs::new()?;
+
+        let mut registry = SchemeRegistry::new();
+        for (kind, dir) in app_dirs.iter() {
+            let scheme = match kind {
+                DirKind::Temporary => "app-temp".to_string(),
+                kind => format!("app-{}", kind),
+            };
+            registry.register(scheme, dir)?;
+        }
+        registry.register("home", user_dirs.home_dir())?;
+
+        #[cfg(any(target_os = "android", target_os = "ios"))]
+        registry.register("container", super::container_path()?)?;
+
+        #[cfg(target_os = "android")]
+        registry.register("external", super::external_path()?)?;
+
+        Ok(registry)
+    }
+
+    /// Maps `scheme` to `root`, replacing any root previously registered for it.
+    ///
+    /// Scheme names are case-insensitive and must be valid per RFC 3986. The
+    /// `file` scheme is reserved.
+    pub fn register<S, P>(&mut self, scheme: S, root: P) -> Result<&mut SchemeRegistry, Error>
+    where
+        S: AsRef<str>,
+        P: Into<PathBuf>,
+    {
+        let scheme = scheme.as_ref().to_ascii_lowercase();
+        if !is_valid_scheme(&scheme) || scheme == "file" {
+            return Err(Error::InvalidSchemeName(scheme));
+        }
+
+        let root = root.into();
+        if <&AbsolutePath>::try_from(&*root).is_err() {
+            return Err(Error::NotAbsolute);
+        }
+
+        match self.schemes.iter_mut().find(|(x, _)| *x == scheme) {
+            Some(entry) => entry.1 = root,
+            None => self.schemes.push((scheme, root)),
+        }
+
+        Ok(self)
+    }
+
+    /// The root registered for `scheme`, if any.
+    pub fn root(&self, scheme: &str) -> Option<&Path> {
+        self.schemes
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(scheme))
+            .map(|(_, root)| &**root)
+    }
+
+    /// Resolves a `file:` IRI or an IRI with a registered scheme to a path.
+    ///
+    /// The resolved path is guaranteed to be inside the scheme's root.
+    pub fn resolve(&self, iri: &IriBuf) -> Result<PathBuf, Error> {
+        let scheme = iri.scheme();
+
+        if scheme.as_str().eq_ignore_ascii_case("file") {
+            return Ok(resolve_file_iri(iri)?.to_path_buf());
+        }
+
+        match self.root(scheme.as_str()) {
+            Some(root) => Ok(resolve_container_iri(root.to_path_buf(), iri)?.to_path_buf()),
+            None => Err(Error::UnknownScheme(scheme.as_str().to_string())),
+        }
+    }
+
+    /// Converts a path into the most specific IRI available: the scheme whose
+    /// root is the longest prefix of the path, or a `file:` IRI if none match.
+    pub fn to_iri<P: AsRef<Path>>(&self, path: P) -> Result<IriBuf, Error> {
+        let path = <&AbsolutePath>::try_from(path.as_ref()).map_err(|e| match e {
+            crate::path::absolute::TryFromError::NotAbsolute => Error::NotAbsolute,
+            crate::path::absolute::TryFromError::ContainsRelComponents => Error::InvalidComponent,
+        })?;
+
+        let best = self
+            .schemes
+            .iter()
+            .filter(|(_, root)| path.starts_with(root))
+            .max_by_key(|(_, root)| root.components().count());
+
+        match best {
+            Some((scheme, root)) => relative_iri(scheme, root, path),
+            None => path.to_file_iri(),
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn registry() -> SchemeRegistry {
+        let mut registry = SchemeRegistry::new();
+        registry
+            .register("home", "/home/x")
+            .unwrap()
+            .register("app-data", "/home/x/.local/share/app")
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn resolve() {
+        let registry = registry();
+
+        let iri = IriBuf::new("app-data:/models/x%20y.bin").unwrap();
+        assert_eq!(
+            registry.resolve(&iri).unwrap(),
+            PathBuf::from("/home/x/.local/share/app/models/x y.bin")
+        );
+
+        let iri = IriBuf::new("APP-DATA:/models").unwrap();
+        assert!(registry.resolve(&iri).is_ok());
+
+        let iri = IriBuf::new("app-data:/../../../.ssh/id_rsa").unwrap();
+        assert!(registry.resolve(&iri).is_err());
+
+        let iri = IriBuf::new("FILE:///etc/hosts").unwrap();
+        assert_eq!(registry.resolve(&iri).unwrap(), PathBuf::from("/etc/hosts"));
+
+        let iri = IriBuf::new("app-cache:/thumbs").unwrap();
+        assert!(matches!(
+            registry.resolve(&iri),
+            Err(Error::UnknownScheme(_))
+        ));
+    }
+
+    #[test]
+    fn to_iri() {
+        let registry = registry();
+
+        let iri = registry
+            .to_iri("/home/x/.local/share/app/models/x y.bin")
+            .unwrap();
+        assert_eq!(iri.as_str(), "app-data:/models/x%20y.bin");
+
+        let iri = registry.to_iri("/home/x/Documents").unwrap();
+        assert_eq!(iri.as_str(), "home:/Documents");
+
+        let iri = registry.to_iri("/home/x").unwrap();
+        assert_eq!(iri.as_str(), "home:/");
+
+        let iri = registry.to_iri("/etc/hosts").unwrap();
+        assert_eq!(iri.as_str(), "file:///etc/hosts");
+    }
+
+    #[test]
+    fn invalid_registrations() {
+        let mut registry = SchemeRegistry::new();
+        assert!(registry.register("file", "/tmp").is_err());
+        assert!(registry.register("1abc", "/tmp").is_err());
+        assert!(registry.register("app data", "/tmp").is_err());
+        assert!(registry.register("app-data", "relative").is_err());
+    }
+}
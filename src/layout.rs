@@ -0,0 +1,547 @@
+//! Directory layouts computed from plain inputs rather than from the host, so
+//! that code running on Linux can work out where a Windows or macOS install
+//! keeps its files.
+//!
+//! The native modules, such as `pathos::user`, use the same functions, so a
+//! layout computed here from the same roots matches what they return.
+
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    install::{InstallDir, InstallDirs},
+    AppDirs, DirKind, Platform,
+};
+
+/// The inputs a user layout is computed from: a home directory (or app
+/// container on iOS and Android), and optionally the base directory of any
+/// kind, in place of the platform's default relative to home.
+///
+/// The base directories correspond to, for example, `XDG_CONFIG_HOME` on
+/// Linux, `FOLDERID_RoamingAppData` (`Data`) and `FOLDERID_LocalAppData`
+/// (`Cache`) on Windows, and `~/Library/Caches` on Apple platforms.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Roots {
+    home: PathBuf,
+    known: BTreeMap<DirKind, PathBuf>,
+}
+
+impl Roots {
+    pub fn new<P: Into<PathBuf>>(home: P) -> Roots {
+        Roots {
+            home: home.into(),
+            known: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the base directory of `kind`. Setting `Home` replaces the home
+    /// directory.
+    pub fn set<P: Into<PathBuf>>(&mut self, kind: DirKind, path: P) -> &mut Self {
+        match kind {
+            DirKind::Home => self.home = path.into(),
+            kind => {
+                self.known.insert(kind, path.into());
+            }
+        }
+        self
+    }
+
+    #[inline]
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// The base directory set for `kind`, if any.
+    pub fn get(&self, kind: DirKind) -> Option<&Path> {
+        match kind {
+            DirKind::Home => Some(&self.home),
+            kind => self.known.get(&kind).map(|x| &**x),
+        }
+    }
+
+    fn get_or(&self, kind: DirKind, default: impl FnOnce() -> PathBuf) -> PathBuf {
+        self.get(kind)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(default)
+    }
+}
+
+/// A user's base directories on some platform; the value counterpart of
+/// `UserDirs`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserLayout {
+    home_dir: PathBuf,
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    cache_dir: PathBuf,
+    state_dir: PathBuf,
+    log_dir: PathBuf,
+    runtime_dir: Option<PathBuf>,
+    executables_dir: Option<PathBuf>,
+    temporary_dir: PathBuf,
+}
+
+impl UserLayout {
+    /// Takes a copy of the directories of a `UserDirs`.
+    pub fn of<D: crate::UserDirs>(dirs: &D) -> UserLayout {
+        UserLayout {
+            home_dir: dirs.home_dir().to_path_buf(),
+            config_dir: dirs.config_dir().to_path_buf(),
+            data_dir: dirs.data_dir().to_path_buf(),
+            cache_dir: dirs.cache_dir().to_path_buf(),
+            state_dir: dirs.state_dir().to_path_buf(),
+            log_dir: dirs.log_dir().to_path_buf(),
+            runtime_dir: dirs.runtime_dir().map(Path::to_path_buf),
+            executables_dir: dirs.executables_dir().map(Path::to_path_buf),
+            temporary_dir: dirs.temporary_dir().to_path_buf(),
+        }
+    }
+
+    #[inline]
+    pub fn home_dir(&self) -> &Path {
+        &self.home_dir
+    }
+
+    #[inline]
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    #[inline]
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    #[inline]
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    #[inline]
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+
+    #[inline]
+    pub fn log_dir(&self) -> &Path {
+        &self.log_dir
+    }
+
+    #[inline]
+    pub fn runtime_dir(&self) -> Option<&Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    #[inline]
+    pub fn executables_dir(&self) -> Option<&Path> {
+        self.executables_dir.as_deref()
+    }
+
+    #[inline]
+    pub fn temporary_dir(&self) -> &Path {
+        &self.temporary_dir
+    }
+
+    /// The directory of the given kind, or `None` where the platform has no
+    /// such location.
+    pub fn dir(&self, kind: DirKind) -> Option<&Path> {
+        match kind {
+            DirKind::Home => Some(self.home_dir()),
+            DirKind::Config => Some(self.config_dir()),
+            DirKind::Data => Some(self.data_dir()),
+            DirKind::Cache => Some(self.cache_dir()),
+            DirKind::State => Some(self.state_dir()),
+            DirKind::Log => Some(self.log_dir()),
+            DirKind::Runtime => self.runtime_dir(),
+            DirKind::Executables => self.executables_dir(),
+            DirKind::Temporary => Some(self.temporary_dir()),
+        }
+    }
+}
+
+impl Platform {
+    /// The user directories this platform's backend produces from `roots`.
+    /// See the `UserDirs` documentation for what each maps to.
+    pub fn user_layout(self, roots: &Roots) -> UserLayout {
+        let home = roots.home();
+        let sub = |base: &Path, path: &str| join(self, base, path);
+
+        match self {
+            Platform::Linux => {
+                let state_dir = roots.get_or(DirKind::State, || sub(home, ".local/state"));
+
+                UserLayout {
+                    config_dir: roots.get_or(DirKind::Config, || sub(home, ".config")),
+                    data_dir: roots.get_or(DirKind::Data, || sub(home, ".local/share")),
+                    cache_dir: roots.get_or(DirKind::Cache, || sub(home, ".cache")),
+                    log_dir: roots.get_or(DirKind::Log, || state_dir.clone()),
+                    state_dir,
+                    runtime_dir: roots.get(DirKind::Runtime).map(Path::to_path_buf),
+                    executables_dir: Some(
+                        roots.get_or(DirKind::Executables, || sub(home, ".local/bin")),
+                    ),
+                    temporary_dir: roots.get_or(DirKind::Temporary, || PathBuf::from("/tmp")),
+                    home_dir: home.to_path_buf(),
+                }
+            }
+            Platform::MacOS | Platform::Ios => {
+                let library = sub(home, "Library");
+                let data_dir = roots.get_or(DirKind::Data, || sub(&library, "Application Support"));
+                let temporary_dir = roots.get_or(DirKind::Temporary, || match self {
+                    Platform::Ios => sub(home, "tmp"),
+                    _ => PathBuf::from("/tmp"),
+                });
+                let executables_dir = match self {
+                    Platform::Ios => None,
+                    _ => Some(roots.get_or(DirKind::Executables, || sub(home, ".local/bin"))),
+                };
+
+                UserLayout {
+                    config_dir: roots.get_or(DirKind::Config, || sub(&library, "Preferences")),
+                    cache_dir: roots.get_or(DirKind::Cache, || sub(&library, "Caches")),
+                    state_dir: roots.get_or(DirKind::State, || data_dir.clone()),
+                    log_dir: roots.get_or(DirKind::Log, || sub(&library, "Logs")),
+                    data_dir,
+                    runtime_dir: Some(roots.get_or(DirKind::Runtime, || temporary_dir.clone())),
+                    executables_dir,
+                    temporary_dir,
+                    home_dir: home.to_path_buf(),
+                }
+            }
+            Platform::Windows => {
+                let data_dir = roots.get_or(DirKind::Data, || sub(home, r"AppData\Roaming"));
+                let local_dir = roots.get_or(DirKind::Cache, || sub(home, r"AppData\Local"));
+
+                UserLayout {
+                    config_dir: roots.get_or(DirKind::Config, || data_dir.clone()),
+                    data_dir,
+                    state_dir: roots.get_or(DirKind::State, || local_dir.clone()),
+                    log_dir: roots.get_or(DirKind::Log, || local_dir.clone()),
+                    runtime_dir: None,
+                    executables_dir: Some(
+                        roots.get_or(DirKind::Executables, || sub(&local_dir, "Programs")),
+                    ),
+                    temporary_dir: roots.get_or(DirKind::Temporary, || sub(&local_dir, "Temp")),
+                    cache_dir: local_dir,
+                    home_dir: home.to_path_buf(),
+                }
+            }
+            Platform::Android => {
+                let data_dir = roots.get_or(DirKind::Data, || sub(home, "files"));
+                let cache_dir = roots.get_or(DirKind::Cache, || sub(home, "cache"));
+                let state_dir = roots.get_or(DirKind::State, || sub(home, "no_backup"));
+
+                UserLayout {
+                    config_dir: roots.get_or(DirKind::Config, || data_dir.clone()),
+                    data_dir,
+                    log_dir: roots.get_or(DirKind::Log, || state_dir.clone()),
+                    state_dir,
+                    runtime_dir: None,
+                    executables_dir: None,
+                    temporary_dir: roots.get_or(DirKind::Temporary, || cache_dir.clone()),
+                    cache_dir,
+                    home_dir: home.to_path_buf(),
+                }
+            }
+        }
+    }
+
+    /// The kind of the user directory that `app_layout` places an app
+    /// directory of the given kind within, or `None` for kinds an app doesn't
+    /// have. `AppDirs::explain` uses it to report where an app directory
+    /// came from.
+    pub fn app_base(self, kind: DirKind) -> Option<DirKind> {
+        use DirKind::*;
+
+        let base = match (self, kind) {
+            (_, Home) | (_, Runtime) | (_, Executables) => return None,
+            (_, Data) => Data,
+            (_, Cache) | (_, Temporary) => Cache,
+            (Platform::Linux, Config) => Config,
+            (_, Config) => Data,
+            (Platform::Linux, Log) | (Platform::Windows, Log) => Data,
+            (_, Log) => Log,
+            (Platform::MacOS, State) | (Platform::Ios, State) => Data,
+            (_, State) => State,
+        };
+
+        Some(base)
+    }
+
+    /// The directories this platform's backend lays out for an app with the
+    /// given prefix, within the user's directories.
+    pub fn app_layout<P: AsRef<Path>>(self, user: &UserLayout, prefix: P) -> AppDirs {
+        let prefix = prefix.as_ref();
+        let sub = |base: &Path, path: &str| join(self, base, path);
+        let at = |base: &Path| join_path(self, base, prefix);
+
+        match self {
+            Platform::Linux => {
+                let data_dir = at(user.data_dir());
+                let cache_dir = at(user.cache_dir());
+
+                let log_dir = sub(&data_dir, "log");
+                let temporary_dir = sub(&cache_dir, "tmp");
+
+                AppDirs::new(
+                    data_dir,
+                    at(user.config_dir()),
+                    cache_dir,
+                    log_dir,
+                    at(user.state_dir()),
+                    temporary_dir,
+                )
+            }
+            Platform::MacOS | Platform::Ios => {
+                let data_dir = at(user.data_dir());
+                let cache_dir = at(user.cache_dir());
+
+                AppDirs::new(
+                    data_dir.clone(),
+                    sub(&data_dir, "config"),
+                    cache_dir.clone(),
+                    at(user.log_dir()),
+                    sub(&data_dir, "state"),
+                    sub(&cache_dir, "tmp"),
+                )
+            }
+            Platform::Windows => {
+                let data_dir = at(user.data_dir());
+                let cache_dir = sub(&at(user.cache_dir()), "cache");
+
+                AppDirs::new(
+                    data_dir.clone(),
+                    sub(&data_dir, "config"),
+                    cache_dir.clone(),
+                    sub(&data_dir, "log"),
+                    sub(&at(user.state_dir()), "state"),
+                    sub(&cache_dir, "tmp"),
+                )
+            }
+            Platform::Android => {
+                let data_dir = at(user.data_dir());
+                let cache_dir = at(user.cache_dir());
+
+                AppDirs::new(
+                    data_dir.clone(),
+                    sub(&data_dir, "config"),
+                    cache_dir.clone(),
+                    sub(&at(user.log_dir()), "log"),
+                    sub(&at(user.state_dir()), "state"),
+                    sub(&cache_dir, "tmp"),
+                )
+            }
+        }
+    }
+
+    /// The system-wide directories this platform's backend lays out for an
+    /// app with the given prefix, or `None` on iOS and Android, whose apps
+    /// have no system-wide directories of their own.
+    ///
+    /// On Linux, this is `InstallDirs` with a prefix of `/usr`.
+    ///
+    /// `root` is where the system directories are found: the file system
+    /// root (`/`) on Linux and macOS, and `FOLDERID_ProgramData`
+    /// (`C:\ProgramData`) on Windows. `None` uses those defaults.
+    pub fn system_app_layout<P: AsRef<Path>>(
+        self,
+        root: Option<&Path>,
+        prefix: P,
+    ) -> Option<AppDirs> {
+        let prefix = prefix.as_ref();
+        let sub = |base: &Path, path: &str| join(self, base, path);
+        let at = |base: &Path| join_path(self, base, prefix);
+
+        match self {
+            Platform::Linux => {
+                let root = root.unwrap_or_else(|| Path::new("/"));
+                let mut install = InstallDirs::new(sub(root, "usr"));
+                install
+                    .set(InstallDir::Sysconfdir, sub(root, "etc"))
+                    .set(InstallDir::Localstatedir, sub(root, "var"));
+
+                Some(install.app_layout(prefix))
+            }
+            Platform::MacOS => {
+                let library = sub(root.unwrap_or_else(|| Path::new("/")), "Library");
+                let data_dir = at(&sub(&library, "Application Support"));
+                let cache_dir = at(&sub(&library, "Caches"));
+
+                Some(AppDirs::new(
+                    data_dir.clone(),
+                    sub(&data_dir, "config"),
+                    cache_dir.clone(),
+                    at(&sub(&library, "Logs")),
+                    sub(&data_dir, "state"),
+                    sub(&cache_dir, "tmp"),
+                ))
+            }
+            Platform::Windows => {
+                let data_dir = at(root.unwrap_or_else(|| Path::new(r"C:\ProgramData")));
+                let cache_dir = sub(&data_dir, "cache");
+
+                Some(AppDirs::new(
+                    data_dir.clone(),
+                    sub(&data_dir, "config"),
+                    cache_dir.clone(),
+                    sub(&data_dir, "log"),
+                    sub(&data_dir, "state"),
+                    sub(&cache_dir, "tmp"),
+                ))
+            }
+            Platform::Ios | Platform::Android => None,
+        }
+    }
+}
+
+/// Joins a relative path written with `/` onto `base`, using the platform's
+/// separator.
+fn join(platform: Platform, base: &Path, path: &str) -> PathBuf {
+    if platform.is_windows() {
+        join_path(platform, base, Path::new(&path.replace('/', "\\")))
+    } else {
+        join_path(platform, base, Path::new(path))
+    }
+}
+
+/// Joins `path` onto `base`, using the platform's separator, so that a Windows
+/// layout computed on Linux reads `C:\Users\x\AppData` rather than
+/// `C:\Users\x/AppData`.
+fn join_path(platform: Platform, base: &Path, path: &Path) -> PathBuf {
+    if !platform.is_windows() || cfg!(windows) {
+        return base.join(path);
+    }
+
+    let mut out = base.as_os_str().to_os_string();
+    for component in path.components() {
+        if let Component::Normal(part) = component {
+            let lossy = out.to_string_lossy();
+            if !lossy.is_empty() && !lossy.ends_with('\\') && !lossy.ends_with('/') {
+                out.push("\\");
+            }
+            out.push(part);
+        }
+    }
+    PathBuf::from(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_base() {
+        let mut roots = Roots::new("/h");
+        for kind in DirKind::ALL.iter() {
+            if *kind != DirKind::Home {
+                roots.set(*kind, format!("/{}", kind));
+            }
+        }
+
+        let platforms = [
+            Platform::Linux,
+            Platform::MacOS,
+            Platform::Ios,
+            Platform::Windows,
+            Platform::Android,
+        ];
+
+        for platform in platforms.iter() {
+            let user = platform.user_layout(&roots);
+            let app = platform.app_layout(&user, "app");
+
+            for (kind, dir) in app.iter() {
+                let base = platform.app_base(kind).unwrap();
+                // Compared as strings, as Windows paths don't split into
+                // components on other hosts.
+                let base_dir = user.dir(base).unwrap().to_string_lossy();
+                assert!(
+                    dir.to_string_lossy().starts_with(&*base_dir),
+                    "{:?} {} is not within {}",
+                    platform,
+                    kind,
+                    base
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn windows_on_any_host() {
+        let user = Platform::Windows.user_layout(&Roots::new(r"C:\Users\x"));
+        assert_eq!(user.data_dir(), Path::new(r"C:\Users\x\AppData\Roaming"));
+        assert_eq!(
+            user.executables_dir(),
+            Some(Path::new(r"C:\Users\x\AppData\Local\Programs"))
+        );
+        assert_eq!(user.runtime_dir(), None);
+
+        let prefix = Platform::Windows.project_prefix("com", "Foo Corp", "Bar App");
+        let app = Platform::Windows.app_layout(&user, &prefix);
+        assert_eq!(
+            app.temporary_dir(),
+            Path::new(r"C:\Users\x\AppData\Local\Foo Corp\Bar App\cache\tmp")
+        );
+
+        let system = Platform::Windows.system_app_layout(None, &prefix).unwrap();
+        assert_eq!(
+            system.log_dir(),
+            Path::new(r"C:\ProgramData\Foo Corp\Bar App\log")
+        );
+    }
+
+    #[test]
+    fn macos() {
+        let mut roots = Roots::new("/Users/x");
+        roots.set(DirKind::Temporary, "/var/folders/xx/T");
+
+        let user = Platform::MacOS.user_layout(&roots);
+        assert_eq!(user.config_dir(), Path::new("/Users/x/Library/Preferences"));
+        assert_eq!(user.runtime_dir(), Some(Path::new("/var/folders/xx/T")));
+
+        let app = Platform::MacOS.app_layout(&user, "com.example.App");
+        assert_eq!(
+            app.state_dir(),
+            Path::new("/Users/x/Library/Application Support/com.example.App/state")
+        );
+        assert_eq!(
+            app.log_dir(),
+            Path::new("/Users/x/Library/Logs/com.example.App")
+        );
+
+        assert_eq!(Platform::Ios.user_layout(&roots).executables_dir(), None);
+        assert!(Platform::Ios.system_app_layout(None, "x").is_none());
+    }
+
+    #[test]
+    fn linux_overrides() {
+        let mut roots = Roots::new("/home/x");
+        roots.set(DirKind::Config, "/etc/xdg-user");
+
+        let user = Platform::Linux.user_layout(&roots);
+        assert_eq!(user.config_dir(), Path::new("/etc/xdg-user"));
+        assert_eq!(user.log_dir(), Path::new("/home/x/.local/state"));
+        assert_eq!(user.runtime_dir(), None);
+
+        let app = Platform::Linux.app_layout(&user, "app");
+        assert_eq!(app.config_dir(), Path::new("/etc/xdg-user/app"));
+        assert_eq!(app.temporary_dir(), Path::new("/home/x/.cache/app/tmp"));
+
+        let system = Platform::Linux.system_app_layout(None, "app").unwrap();
+        assert_eq!(system.state_dir(), Path::new("/var/lib/app"));
+    }
+
+    #[test]
+    fn android() {
+        let user = Platform::Android.user_layout(&Roots::new("/data/user/0/com.example"));
+        let app = Platform::Android.app_layout(&user, "app");
+        assert_eq!(
+            app.state_dir(),
+            Path::new("/data/user/0/com.example/no_backup/app/state")
+        );
+        assert_eq!(user.temporary_dir(), user.cache_dir());
+    }
+}
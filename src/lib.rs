@@ -2,33 +2,56 @@
 
 macro_rules! dir {
     (| $x:ident | $($input:tt)*) => {
-        DIRS.as_ref()
-            .map(|$x| $($input)*)
-            .map_err(|e| e.clone())
+        DIRS.get().map(|$x| $($input)*)
     }
 }
 
+mod app_dirs;
+mod app_name;
+pub mod backend;
+pub mod diagnose;
+mod dir_kind;
+pub mod install;
 pub mod iri;
+pub mod layout;
 pub mod path;
+mod platform;
+mod registry;
+mod resolver;
+mod scope;
+pub mod strategy;
+pub mod sysroot;
 
 #[cfg(any(feature = "android", target_os = "android"))]
 pub mod android;
 #[cfg(any(feature = "ios", target_os = "ios"))]
 pub mod ios;
-#[cfg(any(feature = "linux", target_os = "linux"))]
+#[cfg(any(
+    feature = "linux",
+    target_os = "wasi",
+    all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )
+))]
 pub mod linux;
 #[cfg(any(feature = "macos", target_os = "macos", target_os = "ios"))]
 pub mod macos;
 #[cfg(any(feature = "windows", target_os = "windows"))]
 pub mod windows;
-#[cfg(any(unix, feature = "xdg"))]
 pub mod xdg;
 
 #[cfg(target_os = "android")]
 pub use android::system;
 #[cfg(target_os = "ios")]
 pub use ios::system;
-#[cfg(target_os = "linux")]
+#[cfg(any(
+    target_os = "wasi",
+    all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )
+))]
 pub use linux::system;
 #[cfg(target_os = "macos")]
 pub use macos::system;
@@ -39,13 +62,31 @@ pub use windows::system;
 pub use android::user;
 #[cfg(target_os = "ios")]
 pub use ios::user;
-#[cfg(target_os = "linux")]
+#[cfg(any(
+    target_os = "wasi",
+    all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    )
+))]
 pub use linux::user;
 #[cfg(target_os = "macos")]
 pub use macos::user;
 #[cfg(windows)]
 pub use windows::user;
 
+pub use app_dirs::AppDirs;
+pub use app_name::AppName;
+pub use backend::{backend, set_backend, Backend};
+#[cfg(any(unix, windows, target_os = "wasi"))]
+pub use diagnose::diagnose;
+pub use dir_kind::{DirIter, DirKind, UnknownDirKind};
+pub use platform::Platform;
+pub use registry::Registry;
+pub use resolver::Resolver;
+pub use scope::{Scope, UnknownScope};
+pub use strategy::Strategy;
+
 use std::path::{Path, PathBuf};
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -66,21 +107,48 @@ pub enum Error {
     PathNotAbsolute(#[from] crate::path::absolute::TryFromError),
 }
 
-pub trait AppDirs: Sized {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>;
-    fn create(&self) -> Result<(), Error>;
-    fn data_dir(&self) -> &Path;
-    fn config_dir(&self) -> &Path;
-    fn cache_dir(&self) -> &Path;
-    fn log_dir(&self) -> &Path;
-    fn temporary_dir(&self) -> &Path;
-}
-
+/// The base directories of the current user.
+///
+/// | Method            | Linux               | macOS / iOS                     | Windows                  | Android       |
+/// |-------------------|---------------------|---------------------------------|--------------------------|---------------|
+/// | `home_dir`        | `$HOME`             | home / app container            | `%USERPROFILE%`          | app container |
+/// | `config_dir`      | `$XDG_CONFIG_HOME`  | `~/Library/Preferences`         | `AppData\Roaming`        | `files`       |
+/// | `data_dir`        | `$XDG_DATA_HOME`    | `~/Library/Application Support` | `AppData\Roaming`        | `files`       |
+/// | `cache_dir`       | `$XDG_CACHE_HOME`   | `~/Library/Caches`              | `AppData\Local`          | `cache`       |
+/// | `state_dir`       | `$XDG_STATE_HOME`   | `~/Library/Application Support` | `AppData\Local`          | `no_backup`   |
+/// | `log_dir`         | `$XDG_STATE_HOME`   | `~/Library/Logs`                | `AppData\Local`          | `no_backup`   |
+/// | `runtime_dir`     | `$XDG_RUNTIME_DIR`  | `$TMPDIR`                       | none                     | none          |
+/// | `executables_dir` | `~/.local/bin`      | `~/.local/bin` (macOS only)     | `AppData\Local\Programs` | none          |
+/// | `temporary_dir`   | `$TMPDIR` or `/tmp` | `$TMPDIR`                       | `%TEMP%`                 | `cache`       |
+///
+/// `runtime_dir` and `executables_dir` return `None` where the platform has
+/// no such location. On Linux, `runtime_dir` is also `None` if
+/// `XDG_RUNTIME_DIR` is unset or fails validation.
 pub trait UserDirs: Sized {
     fn new() -> Result<Self, Error>;
     fn home_dir(&self) -> &Path;
+    fn config_dir(&self) -> &Path;
     fn data_dir(&self) -> &Path;
     fn cache_dir(&self) -> &Path;
+    fn state_dir(&self) -> &Path;
+    fn log_dir(&self) -> &Path;
+    fn runtime_dir(&self) -> Option<&Path>;
+    fn executables_dir(&self) -> Option<&Path>;
+    fn temporary_dir(&self) -> &Path;
+
+    /// The directory of the given kind, or `None` where the platform has no
+    /// such location.
+    fn dir(&self, kind: DirKind) -> Option<&Path> {
+        match kind {
+            DirKind::Home => Some(self.home_dir()),
+            DirKind::Config => Some(self.config_dir()),
+            DirKind::Data => Some(self.data_dir()),
+            DirKind::Cache => Some(self.cache_dir()),
+            DirKind::State => Some(self.state_dir()),
+            DirKind::Log => Some(self.log_dir()),
+            DirKind::Runtime => self.runtime_dir(),
+            DirKind::Executables => self.executables_dir(),
+            DirKind::Temporary => Some(self.temporary_dir()),
+        }
+    }
 }
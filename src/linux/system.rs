@@ -1,99 +1,207 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
 
-use crate::Error;
+use once_cell::sync::Lazy;
 
-pub struct AppDirs {
-    data_dir: PathBuf,
-    config_dir: PathBuf,
-    cache_dir: PathBuf,
-    log_dir: PathBuf,
-    temporary_dir: PathBuf,
-}
+use crate::{
+    install::InstallDirs,
+    sysroot::{RootedAppDirs, Sysroot},
+    AppDirs, Error,
+};
 
-impl crate::AppDirs for AppDirs {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-    {
-        let prefix = prefix.into();
+static INSTALL: Lazy<RwLock<InstallDirs>> = Lazy::new(|| RwLock::new(InstallDirs::compiled()));
 
-        let data_dir = app_data_dir(&prefix);
-        let cache_dir = app_cache_dir(&prefix);
+/// The installation directories the system directories are derived from:
+/// `InstallDirs::compiled()` unless replaced with `set_install_dirs`.
+pub fn install_dirs() -> InstallDirs {
+    INSTALL.read().unwrap().clone()
+}
 
-        let user_dirs = Self {
-            config_dir: data_dir.join("config"),
-            temporary_dir: cache_dir.join("tmp"),
-            log_dir: app_log_dir(&prefix),
-            data_dir,
-            cache_dir,
-        };
+/// Replaces the installation directories, e.g. for a package installed under
+/// `/opt/vendor/app` whose prefix is only known at runtime.
+pub fn set_install_dirs(dirs: InstallDirs) {
+    *INSTALL.write().unwrap() = dirs;
+}
 
-        user_dirs.create()?;
+/// The system-wide directories for `prefix`, without creating them.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    Ok(layout(prefix))
+}
 
-        Ok(user_dirs)
-    }
+/// `app_layout`, which can't fail here. The `app_*` functions below all
+/// derive from it, so they can't disagree with it.
+#[inline]
+fn layout<P: AsRef<Path>>(prefix: P) -> AppDirs {
+    install_dirs().app_layout(prefix)
+}
 
-    fn create(&self) -> Result<(), Error> {
-        let dirs = [
-            self.data_dir(),
-            self.config_dir(),
-            self.cache_dir(),
-            self.temporary_dir(),
-            self.log_dir(),
-        ];
-
-        for dir in dirs.iter() {
-            std::fs::create_dir_all(dir).map_err(|e| {
-                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
-            })?;
-        }
-        // TODO: set tmp writable only by creator.
-
-        Ok(())
-    }
+/// The system-wide directories for `prefix` in the image at `sysroot`,
+/// without creating them.
+pub fn app_layout_in<P: AsRef<Path>>(sysroot: &Sysroot, prefix: P) -> Result<RootedAppDirs, Error> {
+    app_layout(prefix).map(|x| sysroot.app_dirs(x))
+}
 
-    fn data_dir(&self) -> &Path {
-        &self.data_dir
-    }
+#[inline]
+pub fn data_dir() -> PathBuf {
+    install_dirs().datadir()
+}
 
-    fn config_dir(&self) -> &Path {
-        &self.config_dir
-    }
+#[inline]
+pub fn config_dir() -> PathBuf {
+    install_dirs().sysconfdir()
+}
 
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
-    }
+#[inline]
+pub fn cache_dir() -> PathBuf {
+    install_dirs().localstatedir().join("cache")
+}
 
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
-    }
+#[inline]
+pub fn temporary_dir() -> PathBuf {
+    install_dirs().localstatedir().join("tmp")
+}
 
-    fn temporary_dir(&self) -> &Path {
-        &self.temporary_dir
-    }
+#[inline]
+pub fn log_dir() -> PathBuf {
+    install_dirs().localstatedir().join("log")
+}
+
+#[inline]
+pub fn state_dir() -> PathBuf {
+    install_dirs().localstatedir().join("lib")
 }
 
 #[inline]
 pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    PathBuf::from("/usr/share/").join(prefix)
+    layout(prefix).data_dir().to_path_buf()
 }
 
 #[inline]
 pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    PathBuf::from("/etc/").join(prefix)
+    layout(prefix).config_dir().to_path_buf()
 }
 
 #[inline]
 pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    PathBuf::from("/var/cache/").join(prefix)
+    layout(prefix).cache_dir().to_path_buf()
 }
 
 #[inline]
 pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    PathBuf::from("/var/tmp/").join(prefix)
+    layout(prefix).temporary_dir().to_path_buf()
 }
 
 #[inline]
 pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    PathBuf::from("/var/log/").join(prefix)
+    layout(prefix).log_dir().to_path_buf()
+}
+
+#[inline]
+pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
+    layout(prefix).state_dir().to_path_buf()
+}
+
+pub mod iri {
+    use crate::{iri::dir_iri, Error};
+    use iref::IriBuf;
+    use std::path::Path;
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::data_dir())
+    }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::config_dir())
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::cache_dir())
+    }
+
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::temporary_dir())
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::log_dir())
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::state_dir())
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_data_dir(prefix))
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_config_dir(prefix))
+    }
+
+    #[inline]
+    pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_cache_dir(prefix))
+    }
+
+    #[inline]
+    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_temporary_dir(prefix))
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_log_dir(prefix))
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_state_dir(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iri::dir_iri;
+
+    #[test]
+    fn app_dirs_match_layout() {
+        let install = InstallDirs::compiled();
+        let dirs = app_layout("app").unwrap();
+        assert_eq!(app_config_dir("app"), dirs.config_dir());
+        assert_eq!(app_temporary_dir("app"), dirs.temporary_dir());
+        assert_eq!(
+            app_temporary_dir("app"),
+            install.localstatedir().join("tmp").join("app")
+        );
+    }
+
+    #[test]
+    fn iris() {
+        let install = InstallDirs::compiled();
+        assert_eq!(
+            super::iri::log_dir().unwrap(),
+            dir_iri(install.localstatedir().join("log")).unwrap()
+        );
+        assert_eq!(
+            super::iri::app_state_dir("Special Company/Bad App").unwrap(),
+            dir_iri(
+                install
+                    .localstatedir()
+                    .join("lib")
+                    .join("Special Company/Bad App")
+            )
+            .unwrap()
+        );
+    }
 }
@@ -1,23 +1,73 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use once_cell::sync::Lazy;
 
-use crate::{xdg, xdg::home_dir, AppDirs as _, Error, UserDirs};
+use crate::{
+    diagnose::{Provenance, Source},
+    layout::UserLayout,
+    xdg, AppDirs, DirKind, Error, Platform, Registry, Resolver, UserDirs,
+};
 
-static DIRS: Lazy<Result<Dirs, Error>> = Lazy::new(|| UserDirs::new());
+static DIRS: Resolver<Dirs> = Resolver::new(Dirs::new);
 
+/// The resolver behind the free functions in this module.
+#[inline]
+pub fn resolver() -> &'static Resolver<Dirs> {
+    &DIRS
+}
+
+/// Resolves the user's directories again, such as after `XDG_*` variables
+/// have changed. Paths returned earlier remain valid. This also forgets the
+/// app directories cached by `app_dirs`.
+#[inline]
+pub fn reload() -> Result<(), Error> {
+    DIRS.reload()?;
+    APPS.clear();
+    Ok(())
+}
+
+static APPS: Lazy<Registry> = Lazy::new(|| Registry::new(|prefix| app_layout(prefix)));
+
+/// The registry behind `app_dirs` and the `app_*` functions in this module.
+#[inline]
+pub fn registry() -> &'static Registry {
+    &APPS
+}
+
+/// The directories for `prefix`, created on first use and shared after.
+#[inline]
+pub fn app_dirs<P: Into<PathBuf>>(prefix: P) -> Result<Arc<AppDirs>, Error> {
+    APPS.get(prefix)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Dirs {
     home_dir: PathBuf,
+    config_dir: PathBuf,
     data_dir: PathBuf,
     cache_dir: PathBuf,
+    state_dir: PathBuf,
+    runtime_dir: Option<PathBuf>,
+    executables_dir: PathBuf,
+    temporary_dir: PathBuf,
 }
 
 impl UserDirs for Dirs {
     fn new() -> Result<Self, Error> {
+        let home_dir = xdg::home_dir()?;
+
         Ok(Self {
-            home_dir: home_dir()?,
+            config_dir: xdg::config_home()?,
             data_dir: xdg::data_home()?,
             cache_dir: xdg::cache_home()?,
+            state_dir: xdg::state_home()?,
+            runtime_dir: xdg::runtime_dir().ok(),
+            executables_dir: xdg::executables_home()?,
+            temporary_dir: std::env::temp_dir(),
+            home_dir,
         })
     }
 
@@ -25,6 +75,10 @@ impl UserDirs for Dirs {
         &self.home_dir
     }
 
+    fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
     fn data_dir(&self) -> &Path {
         &self.data_dir
     }
@@ -32,75 +86,22 @@ impl UserDirs for Dirs {
     fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
-}
-
-pub struct AppDirs {
-    data_dir: PathBuf,
-    config_dir: PathBuf,
-    cache_dir: PathBuf,
-    log_dir: PathBuf,
-    temporary_dir: PathBuf,
-}
-
-impl crate::AppDirs for AppDirs {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-    {
-        let prefix = prefix.into();
-
-        let data_dir = xdg::data_home()?.join(&prefix);
-        let config_dir = xdg::config_home()?.join(&prefix);
-        let cache_dir = xdg::cache_home()?.join(&prefix);
-        let temporary_dir = cache_dir.join("tmp");
-        let log_dir = data_dir.join("log");
-
-        let user_dirs = Self {
-            config_dir,
-            temporary_dir,
-            log_dir,
-            data_dir,
-            cache_dir,
-        };
-
-        user_dirs.create()?;
-
-        Ok(user_dirs)
-    }
-
-    fn create(&self) -> Result<(), Error> {
-        let dirs = [
-            self.data_dir(),
-            self.config_dir(),
-            self.cache_dir(),
-            self.temporary_dir(),
-            self.log_dir(),
-        ];
-
-        for dir in dirs.iter() {
-            std::fs::create_dir_all(dir).map_err(|e| {
-                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
-            })?;
-        }
-        // TODO: set tmp writable only by creator.
 
-        Ok(())
+    fn state_dir(&self) -> &Path {
+        &self.state_dir
     }
 
-    fn data_dir(&self) -> &Path {
-        &self.data_dir
-    }
-
-    fn config_dir(&self) -> &Path {
-        &self.config_dir
+    /// XDG has no log directory; logs belong in `$XDG_STATE_HOME`.
+    fn log_dir(&self) -> &Path {
+        &self.state_dir
     }
 
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
+    fn runtime_dir(&self) -> Option<&Path> {
+        self.runtime_dir.as_deref()
     }
 
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
+    fn executables_dir(&self) -> Option<&Path> {
+        Some(&self.executables_dir)
     }
 
     fn temporary_dir(&self) -> &Path {
@@ -108,6 +109,37 @@ impl crate::AppDirs for AppDirs {
     }
 }
 
+/// The current user's directories for `prefix`, without creating them.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    let user = UserLayout::of(DIRS.get()?);
+    Ok(Platform::Linux
+        .app_layout(&user, prefix)
+        .with_provenance(xdg::app_provenance))
+}
+
+/// How the directory of each kind is chosen, for `pathos::diagnose`.
+pub(crate) fn provenance(kind: DirKind) -> Provenance {
+    match kind {
+        DirKind::Home => Provenance::env("HOME", Source::System),
+        DirKind::Config | DirKind::Data | DirKind::Cache | DirKind::State | DirKind::Log => {
+            xdg::provenance(kind)
+        }
+        DirKind::Runtime => xdg::runtime_dir_provenance(),
+        DirKind::Executables => Provenance::default(),
+        DirKind::Temporary => Provenance::env("TMPDIR", Source::Default),
+    }
+}
+
+#[inline]
+pub fn home_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.home_dir())
+}
+
+#[inline]
+pub fn config_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.config_dir())
+}
+
 #[inline]
 pub fn data_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.data_dir())
@@ -118,47 +150,160 @@ pub fn cache_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.cache_dir())
 }
 
+#[inline]
+pub fn state_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.state_dir())
+}
+
+#[inline]
+pub fn log_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.log_dir())
+}
+
+#[inline]
+pub fn runtime_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.runtime_dir())?.ok_or(Error::NotFound("Runtime"))
+}
+
+#[inline]
+pub fn executables_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.executables_dir())?.ok_or(Error::NotFound("Executables"))
+}
+
+#[inline]
+pub fn temporary_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.temporary_dir())
+}
+
 #[inline]
 pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.data_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.data_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.config_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.config_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.log_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.log_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.cache_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.cache_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.state_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.temporary_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.temporary_dir().to_path_buf())
 }
 
 pub mod iri {
-    use crate::{path::absolute::AbsolutePathBufExt, Error};
+    use crate::{iri::dir_iri, Error};
     use iref::IriBuf;
     use std::path::PathBuf;
 
+    #[inline]
+    pub fn home_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::home_dir()?)
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::data_dir()?)
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::cache_dir()?)
+    }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::config_dir()?)
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::state_dir()?)
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::log_dir()?)
+    }
+
+    #[inline]
+    pub fn runtime_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::runtime_dir()?)
+    }
+
+    #[inline]
+    pub fn executables_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::executables_dir()?)
+    }
+
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::temporary_dir()?)
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_data_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_config_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_log_dir(prefix)?)
+    }
+
     #[inline]
     pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_cache_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_cache_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_state_dir(prefix)?)
     }
 
     #[inline]
     pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_temporary_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_temporary_dir(prefix)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnose;
+
+    #[test]
+    fn app_sources() {
+        fn base(kind: DirKind) -> Provenance {
+            Provenance::new(Source::Env(match kind {
+                DirKind::Data => "DATA",
+                DirKind::Cache => "CACHE",
+                _ => "OTHER",
+            }))
+        }
+
+        let source = |kind| diagnose::app_provenance(Platform::Linux, kind, base).source;
+        assert_eq!(source(DirKind::Log), Source::Env("DATA"));
+        assert_eq!(source(DirKind::Temporary), Source::Env("CACHE"));
+        assert_eq!(source(DirKind::Runtime), Source::Default);
     }
 }
@@ -1,36 +1,30 @@
-macro_rules! static_path {
-    ($directory:path, $domain:path) => {{
-        static CELL: once_cell::sync::OnceCell<Result<PathBuf, $crate::Error>> =
-            once_cell::sync::OnceCell::new();
-        CELL.get_or_init(|| {
-            let ns_url = $crate::macos::FILE_MANAGER
-                .url_for_directory($directory, $domain, None, false)
-                .unwrap();
-            let ns_string = ns_url.path();
-            Ok(PathBuf::from(ns_string.to_string()))
-        })
-    }};
-    ($directory:path, $domain:path, $extra:tt) => {{
-        static CELL: once_cell::sync::OnceCell<Result<PathBuf, $crate::Error>> =
-            once_cell::sync::OnceCell::new();
-        CELL.get_or_init(|| {
-            let ns_url = $crate::macos::FILE_MANAGER
-                .url_for_directory($directory, $domain, None, false)
-                .unwrap();
-            let ns_string = ns_url.path();
-            Ok(PathBuf::from(format!(
-                "{}/{}",
-                ns_string.to_string(),
-                $extra
-            )))
-        })
-    }};
+//! Directories on macOS. The native functions are only available when
+//! compiling for an Apple target; elsewhere, the `macos` feature leaves this
+//! module empty, and `Platform::MacOS.user_layout` computes the same layout
+//! from given roots.
+
+/// Looks up a directory with `NSFileManager`, as a `Result<PathBuf, Error>`.
+/// Nothing is cached here; callers keep the result in a `Resolver` so that a
+/// failed lookup is retried and a successful one can be reloaded.
+#[cfg(target_vendor = "apple")]
+macro_rules! ns_path {
+    ($directory:path, $domain:path) => {
+        $crate::macos::FILE_MANAGER
+            .url_for_directory($directory, $domain, None, false)
+            .map(|ns_url| PathBuf::from(ns_url.path().to_string()))
+            .map_err(|_| $crate::Error::DirectoryResolutionFailed(stringify!($directory)))
+    };
+    ($directory:path, $domain:path, $extra:tt) => {
+        ns_path!($directory, $domain).map(|x| x.join($extra))
+    };
 }
 
+#[cfg(target_vendor = "apple")]
 pub mod system;
+#[cfg(target_vendor = "apple")]
 pub mod user;
 
-use once_cell::sync::Lazy;
-
-static FILE_MANAGER: Lazy<fruity::core::Arc<fruity::foundation::NSFileManager<'_>>> =
-    Lazy::new(|| fruity::foundation::NSFileManager::default_manager());
+#[cfg(target_vendor = "apple")]
+static FILE_MANAGER: once_cell::sync::Lazy<
+    fruity::core::Arc<fruity::foundation::NSFileManager<'_>>,
+> = once_cell::sync::Lazy::new(|| fruity::foundation::NSFileManager::default_manager());
@@ -4,149 +4,158 @@ use fruity::foundation::{
     NSApplicationSupportDirectory, NSCachesDirectory, NSLibraryDirectory, NSLocalDomainMask,
 };
 
-use crate::Error;
+use crate::{AppDirs, Error, Platform, Resolver};
 
-#[inline]
-pub fn services_dir() -> &'static Path {
-    static_path!(NSLibraryDirectory, NSLocalDomainMask, "Services")
-        .as_ref()
-        .unwrap()
+static DIRS: Resolver<Dirs> = Resolver::new(Dirs::resolve);
+
+#[derive(Debug, PartialEq, Eq)]
+struct Dirs {
+    services_dir: PathBuf,
+    application_support_dir: PathBuf,
+    cache_dir: PathBuf,
+    log_dir: PathBuf,
 }
 
-#[inline]
-pub fn application_support_dir() -> &'static Path {
-    static_path!(NSApplicationSupportDirectory, NSLocalDomainMask)
-        .as_ref()
-        .unwrap()
+impl Dirs {
+    fn resolve() -> Result<Dirs, Error> {
+        Ok(Dirs {
+            services_dir: ns_path!(NSLibraryDirectory, NSLocalDomainMask, "Services")?,
+            application_support_dir: ns_path!(NSApplicationSupportDirectory, NSLocalDomainMask)?,
+            cache_dir: ns_path!(NSCachesDirectory, NSLocalDomainMask)?,
+            log_dir: ns_path!(NSLibraryDirectory, NSLocalDomainMask, "Logs")?,
+        })
+    }
 }
 
+/// Looks up the system's directories again. Paths returned earlier remain
+/// valid.
 #[inline]
-pub fn data_dir() -> &'static Path {
-    application_support_dir()
+pub fn reload() -> Result<(), Error> {
+    DIRS.reload().map(|_| ())
 }
 
 #[inline]
-pub fn cache_dir() -> &'static Path {
-    static_path!(NSCachesDirectory, NSLocalDomainMask)
-        .as_ref()
-        .unwrap()
+pub fn services_dir() -> Result<&'static Path, Error> {
+    dir!(|x| &*x.services_dir)
 }
 
 #[inline]
-pub fn log_dir() -> &'static Path {
-    static_path!(NSLibraryDirectory, NSLocalDomainMask, "Logs")
-        .as_ref()
-        .unwrap()
+pub fn application_support_dir() -> Result<&'static Path, Error> {
+    dir!(|x| &*x.application_support_dir)
 }
 
-pub struct AppDirs {
-    data_dir: PathBuf,
-    config_dir: PathBuf,
-    cache_dir: PathBuf,
-    log_dir: PathBuf,
-    temporary_dir: PathBuf,
+#[inline]
+pub fn data_dir() -> Result<&'static Path, Error> {
+    application_support_dir()
 }
 
-impl crate::AppDirs for AppDirs {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-    {
-        let prefix = prefix.into();
-
-        let data_dir = application_support_dir().join(&prefix);
-        let cache_dir = cache_dir().join(&prefix);
-
-        let app_dirs = Self {
-            config_dir: data_dir.join("config"),
-            temporary_dir: cache_dir.join("tmp"),
-            log_dir: log_dir().join(&prefix),
-            data_dir,
-            cache_dir,
-        };
-
-        app_dirs.create()?;
-
-        Ok(app_dirs)
-    }
+#[inline]
+pub fn cache_dir() -> Result<&'static Path, Error> {
+    dir!(|x| &*x.cache_dir)
+}
 
-    fn create(&self) -> Result<(), Error> {
-        let dirs = [
-            self.data_dir(),
-            self.config_dir(),
-            self.cache_dir(),
-            self.temporary_dir(),
-            self.log_dir(),
-        ];
-
-        for dir in dirs.iter() {
-            std::fs::create_dir_all(dir).map_err(|e| {
-                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
-            })?;
-        }
-        // TODO: set tmp writable only by creator.
-
-        Ok(())
-    }
+#[inline]
+pub fn log_dir() -> Result<&'static Path, Error> {
+    dir!(|x| &*x.log_dir)
+}
 
-    fn data_dir(&self) -> &Path {
-        &self.data_dir
-    }
+/// The system-wide directories for `prefix`, without creating them.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    // `/Library/Application Support` is two levels below the root.
+    let root = application_support_dir()?.ancestors().nth(2);
 
-    fn config_dir(&self) -> &Path {
-        &self.config_dir
-    }
-
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
-    }
+    Platform::MacOS
+        .system_app_layout(root, prefix)
+        .ok_or(Error::NotFound("System directories"))
+}
 
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
-    }
+#[inline]
+pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.data_dir().to_path_buf())
+}
 
-    fn temporary_dir(&self) -> &Path {
-        &self.temporary_dir
-    }
+#[inline]
+pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.config_dir().to_path_buf())
 }
 
 #[inline]
-pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    data_dir().join(prefix.as_ref())
+pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.cache_dir().to_path_buf())
 }
 
 #[inline]
-pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    cache_dir().join(prefix.as_ref())
+pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.temporary_dir().to_path_buf())
 }
 
 #[inline]
-pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    app_cache_dir(prefix).join("tmp")
+pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.log_dir().to_path_buf())
 }
 
 #[inline]
-pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> PathBuf {
-    log_dir().join(prefix.as_ref())
+pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.state_dir().to_path_buf())
 }
 
 pub mod iri {
-    use crate::path::absolute::AbsolutePathBufExt;
-    use crate::Error;
+    use crate::{iri::dir_iri, Error};
     use iref::IriBuf;
     use std::path::Path;
 
     #[inline]
-    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_temporary_dir(prefix)
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+    pub fn services_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::services_dir()?)
+    }
+
+    #[inline]
+    pub fn application_support_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::application_support_dir()?)
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::data_dir()?)
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::cache_dir()?)
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::log_dir()?)
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_data_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_config_dir(prefix)?)
     }
 
     #[inline]
     pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_cache_dir(prefix)
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_cache_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_temporary_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_log_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_state_dir(prefix)?)
     }
 }
@@ -1,20 +1,61 @@
 use fruity::foundation::{
     NSApplicationSupportDirectory, NSCachesDirectory, NSLibraryDirectory, NSUserDomainMask,
 };
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
 use once_cell::sync::Lazy;
-use std::path::{Path, PathBuf};
 
-use crate::{AppDirs as _, Error, UserDirs};
+use crate::{
+    diagnose::{self, Provenance, Source},
+    layout::UserLayout,
+    AppDirs, DirKind, Error, Platform, Registry, Resolver, UserDirs,
+};
 
-static DIRS: Lazy<Result<Dirs, Error>> = Lazy::new(|| Dirs::new());
+static DIRS: Resolver<Dirs> = Resolver::new(Dirs::new);
+
+/// The resolver behind the free functions in this module.
+#[inline]
+pub fn resolver() -> &'static Resolver<Dirs> {
+    &DIRS
+}
+
+/// Resolves the user's directories again, such as after `TMPDIR` has
+/// changed. Paths returned earlier remain valid. This also forgets the app
+/// directories cached by `app_dirs`.
+#[inline]
+pub fn reload() -> Result<(), Error> {
+    DIRS.reload()?;
+    APPS.clear();
+    Ok(())
+}
 
+static APPS: Lazy<Registry> = Lazy::new(|| Registry::new(|prefix| app_layout(prefix)));
+
+/// The registry behind `app_dirs` and the `app_*` functions in this module.
+#[inline]
+pub fn registry() -> &'static Registry {
+    &APPS
+}
+
+/// The directories for `prefix`, created on first use and shared after.
+#[inline]
+pub fn app_dirs<P: Into<PathBuf>>(prefix: P) -> Result<Arc<AppDirs>, Error> {
+    APPS.get(prefix)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Dirs {
     home_dir: PathBuf,
-    data_dir: &'static PathBuf,
-    cache_dir: &'static PathBuf,
-    log_dir: &'static PathBuf,
-    prefs_dir: &'static PathBuf,
-    services_dir: &'static PathBuf,
+    data_dir: PathBuf,
+    cache_dir: PathBuf,
+    log_dir: PathBuf,
+    prefs_dir: PathBuf,
+    services_dir: PathBuf,
+    executables_dir: Option<PathBuf>,
+    temporary_dir: PathBuf,
 }
 
 impl Dirs {
@@ -22,10 +63,6 @@ impl Dirs {
         &self.data_dir
     }
 
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
-    }
-
     fn preferences_dir(&self) -> &Path {
         &self.prefs_dir
     }
@@ -36,43 +73,69 @@ impl Dirs {
 }
 
 #[cfg(target_os = "ios")]
-fn _home_dir() -> PathBuf {
+fn _home_dir() -> Result<PathBuf, Error> {
     #[allow(deprecated)]
-    std::env::home_dir()
-        .expect("no container found for current app when attempting to get home dir")
+    std::env::home_dir().ok_or(Error::NotFound("Container"))
+}
+
+/// iOS apps can't install executables for the user.
+#[cfg(target_os = "ios")]
+fn _executables_dir(_home_dir: &Path) -> Option<PathBuf> {
+    None
 }
 
 #[cfg(target_os = "macos")]
-fn _home_dir() -> PathBuf {
-    PathBuf::from(
+fn _home_dir() -> Result<PathBuf, Error> {
+    Ok(PathBuf::from(
         super::FILE_MANAGER
             .home_directory_for_current_user()
             .path()
             .to_string(),
-    )
+    ))
 }
 
-impl UserDirs for Dirs {
-    fn new() -> Result<Self, Error> {
-        let home_dir = _home_dir();
+/// macOS has no native location for user executables, so this follows the
+/// XDG convention that tools such as `pipx` use there too.
+#[cfg(target_os = "macos")]
+fn _executables_dir(home_dir: &Path) -> Option<PathBuf> {
+    Some(home_dir.join(".local/bin"))
+}
 
-        let dirs = (|| -> Result<Dirs, &Error> {
-            // let lib_dir = static_path!(NSLibraryDirectory, NSUserDomainMask).as_ref()?,
+/// How the app directory of each kind is chosen, for `AppDirs::explain`:
+/// that of the user directory it is laid out within.
+fn app_provenance(kind: DirKind) -> Provenance {
+    diagnose::app_provenance(Platform::current(), kind, provenance)
+}
 
-            Ok(Self {
-                data_dir: static_path!(NSApplicationSupportDirectory, NSUserDomainMask).as_ref()?,
-                cache_dir: static_path!(NSCachesDirectory, NSUserDomainMask).as_ref()?,
-                log_dir: static_path!(NSLibraryDirectory, NSUserDomainMask, "Logs").as_ref()?,
-                prefs_dir: static_path!(NSLibraryDirectory, NSUserDomainMask, "Preferences")
-                    .as_ref()?,
-                services_dir: static_path!(NSLibraryDirectory, NSUserDomainMask, "Services")
-                    .as_ref()?,
-                home_dir,
-            })
-        })()
-        .map_err(|e| e.clone())?;
+/// How the directory of each kind is chosen, for `pathos::diagnose`.
+pub(crate) fn provenance(kind: DirKind) -> Provenance {
+    let source = if cfg!(target_os = "ios") {
+        Source::Sandbox
+    } else {
+        Source::System
+    };
+
+    match kind {
+        DirKind::Runtime | DirKind::Temporary => Provenance::env("TMPDIR", source),
+        DirKind::Executables => Provenance::default(),
+        _ => Provenance::new(source),
+    }
+}
 
-        Ok(dirs)
+impl UserDirs for Dirs {
+    fn new() -> Result<Self, Error> {
+        let home_dir = _home_dir()?;
+
+        Ok(Self {
+            data_dir: ns_path!(NSApplicationSupportDirectory, NSUserDomainMask)?,
+            cache_dir: ns_path!(NSCachesDirectory, NSUserDomainMask)?,
+            log_dir: ns_path!(NSLibraryDirectory, NSUserDomainMask, "Logs")?,
+            prefs_dir: ns_path!(NSLibraryDirectory, NSUserDomainMask, "Preferences")?,
+            services_dir: ns_path!(NSLibraryDirectory, NSUserDomainMask, "Services")?,
+            executables_dir: _executables_dir(&home_dir),
+            temporary_dir: std::env::temp_dir(),
+            home_dir,
+        })
     }
 
     fn home_dir(&self) -> &Path {
@@ -83,76 +146,30 @@ impl UserDirs for Dirs {
         &self.data_dir
     }
 
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
-    }
-}
-
-pub struct AppDirs {
-    data_dir: PathBuf,
-    config_dir: PathBuf,
-    cache_dir: PathBuf,
-    log_dir: PathBuf,
-    temporary_dir: PathBuf,
-}
-
-impl crate::AppDirs for AppDirs {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-    {
-        let prefix = prefix.into();
-        let dirs = Dirs::new()?;
-
-        let data_dir = dirs.application_support_dir().join(&prefix);
-        let cache_dir = dirs.cache_dir().join(&prefix);
-
-        let user_dirs = Self {
-            config_dir: data_dir.join("config"),
-            temporary_dir: cache_dir.join("tmp"),
-            log_dir: dirs.log_dir().join(&prefix),
-            data_dir,
-            cache_dir,
-        };
-
-        user_dirs.create()?;
-
-        Ok(user_dirs)
+    fn config_dir(&self) -> &Path {
+        &self.prefs_dir
     }
 
-    fn create(&self) -> Result<(), Error> {
-        let dirs = [
-            self.data_dir(),
-            self.config_dir(),
-            self.cache_dir(),
-            self.temporary_dir(),
-            self.log_dir(),
-        ];
-
-        for dir in dirs.iter() {
-            std::fs::create_dir_all(dir).map_err(|e| {
-                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
-            })?;
-        }
-        // TODO: set tmp writable only by creator.
-
-        Ok(())
+    fn cache_dir(&self) -> &Path {
+        &self.cache_dir
     }
 
-    fn data_dir(&self) -> &Path {
+    fn state_dir(&self) -> &Path {
         &self.data_dir
     }
 
-    fn config_dir(&self) -> &Path {
-        &self.config_dir
+    fn log_dir(&self) -> &Path {
+        &self.log_dir
     }
 
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
+    /// The per-user `$TMPDIR`, which is private to the user (or, on iOS, the
+    /// app) and cleaned up by the system.
+    fn runtime_dir(&self) -> Option<&Path> {
+        Some(&self.temporary_dir)
     }
 
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
+    fn executables_dir(&self) -> Option<&Path> {
+        self.executables_dir.as_deref()
     }
 
     fn temporary_dir(&self) -> &Path {
@@ -160,6 +177,14 @@ impl crate::AppDirs for AppDirs {
     }
 }
 
+/// The current user's directories for `prefix`, without creating them.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    let user = UserLayout::of(DIRS.get()?);
+    Ok(Platform::current()
+        .app_layout(&user, prefix)
+        .with_provenance(app_provenance))
+}
+
 #[inline]
 pub fn home_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.home_dir())
@@ -180,11 +205,36 @@ pub fn preferences_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.preferences_dir())
 }
 
+#[inline]
+pub fn config_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.config_dir())
+}
+
 #[inline]
 pub fn cache_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.cache_dir())
 }
 
+#[inline]
+pub fn state_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.state_dir())
+}
+
+#[inline]
+pub fn runtime_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.runtime_dir())?.ok_or(Error::NotFound("Runtime"))
+}
+
+#[inline]
+pub fn executables_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.executables_dir())?.ok_or(Error::NotFound("Executables"))
+}
+
+#[inline]
+pub fn temporary_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.temporary_dir())
+}
+
 #[inline]
 pub fn log_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.log_dir())
@@ -197,46 +247,126 @@ pub fn services_dir() -> Result<&'static Path, Error> {
 
 #[inline]
 pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.data_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.data_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.config_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.config_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.log_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.log_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.cache_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.cache_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.state_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.temporary_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.temporary_dir().to_path_buf())
 }
 
 pub mod iri {
-    use crate::path::absolute::AbsolutePathBufExt;
-    use crate::Error;
+    use crate::{iri::dir_iri, Error};
     use iref::IriBuf;
     use std::path::PathBuf;
 
+    #[inline]
+    pub fn home_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::home_dir()?)
+    }
+
+    #[inline]
+    pub fn application_support_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::application_support_dir()?)
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::data_dir()?)
+    }
+
+    #[inline]
+    pub fn preferences_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::preferences_dir()?)
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::cache_dir()?)
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::log_dir()?)
+    }
+
+    #[inline]
+    pub fn services_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::services_dir()?)
+    }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::config_dir()?)
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::state_dir()?)
+    }
+
+    #[inline]
+    pub fn runtime_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::runtime_dir()?)
+    }
+
+    #[inline]
+    pub fn executables_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::executables_dir()?)
+    }
+
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::temporary_dir()?)
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_data_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_config_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_log_dir(prefix)?)
+    }
+
     #[inline]
     pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_cache_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_cache_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_state_dir(prefix)?)
     }
 
     #[inline]
     pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_temporary_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_temporary_dir(prefix)?)
     }
 }
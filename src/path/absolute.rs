@@ -1,14 +1,13 @@
 use std::{
-    borrow::Cow,
     convert::{TryFrom, TryInto},
     ffi::{OsStr, OsString},
-    iter::once,
     ops::Deref,
 };
 
 use iref::IriBuf;
 use os_str_bytes::OsStrBytes;
-use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
+
+use crate::iri::IriPathBuilder;
 
 #[derive(Debug, Clone)]
 pub enum TryFromError {
@@ -37,13 +36,13 @@ impl Deref for AbsolutePath {
     type Target = std::path::Path;
 
     fn deref(&self) -> &Self::Target {
-        &std::path::Path::new(&self.0)
+        std::path::Path::new(&self.0)
     }
 }
 
 impl<'a> From<&'a AbsolutePath> for &'a std::path::Path {
     fn from(abs_path: &'a AbsolutePath) -> Self {
-        &std::path::Path::new(&abs_path.0)
+        std::path::Path::new(&abs_path.0)
     }
 }
 
@@ -57,10 +56,9 @@ impl<'a> TryFrom<&'a std::path::Path> for &'a AbsolutePath {
             return Err(TryFromError::NotAbsolute);
         }
 
-        let has_rel_components = value.components().any(|x| match x {
-            Component::CurDir | Component::ParentDir => true,
-            _ => false,
-        });
+        let has_rel_components = value
+            .components()
+            .any(|x| matches!(x, Component::CurDir | Component::ParentDir));
 
         if has_rel_components {
             return Err(TryFromError::ContainsRelComponents);
@@ -77,7 +75,7 @@ impl AbsolutePath {
     }
 
     pub fn to_path(&self) -> &std::path::Path {
-        &std::path::Path::new(&self.0)
+        std::path::Path::new(&self.0)
     }
 
     pub fn to_path_buf(&self) -> std::path::PathBuf {
@@ -87,6 +85,42 @@ impl AbsolutePath {
     pub fn to_absolute_path_buf(&self) -> AbsolutePathBuf {
         AbsolutePathBuf(self.0.to_os_string())
     }
+
+    pub fn to_file_iri(&self) -> Result<IriBuf, crate::iri::Error> {
+        file_path(self.to_path())
+    }
+
+    /// Converts a path inside the app's container into a `container:` IRI,
+    /// which stays valid when the container moves (e.g. on reinstall).
+    ///
+    /// Fails with `OutsideContainer` if the path is not inside the container.
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    pub fn to_container_iri(&self) -> Result<IriBuf, crate::iri::Error> {
+        let container = crate::iri::container_path()?;
+
+        match crate::iri::relative_iri("container", &container, self) {
+            // iOS hands out paths through both `/var` and its `/private/var` target.
+            Err(crate::iri::Error::OutsideContainer) if self.starts_with("/private/var") => {
+                let path = std::path::Path::new("/").join(self.strip_prefix("/private").unwrap());
+                crate::iri::relative_iri(
+                    "container",
+                    &container,
+                    AbsolutePath::new_unchecked(&path),
+                )
+            }
+            result => result,
+        }
+    }
+
+    /// Converts a path into a `container:` IRI if it is inside the app's
+    /// container, and a `file:` IRI otherwise.
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    pub fn to_container_or_file_iri(&self) -> Result<IriBuf, crate::iri::Error> {
+        match self.to_container_iri() {
+            Err(crate::iri::Error::OutsideContainer) => self.to_file_iri(),
+            result => result,
+        }
+    }
 }
 
 #[repr(transparent)]
@@ -103,10 +137,9 @@ impl TryFrom<std::path::PathBuf> for AbsolutePathBuf {
             return Err(TryFromError::NotAbsolute);
         }
 
-        let has_rel_components = value.components().any(|x| match x {
-            Component::CurDir | Component::ParentDir => true,
-            _ => false,
-        });
+        let has_rel_components = value
+            .components()
+            .any(|x| matches!(x, Component::CurDir | Component::ParentDir));
 
         if has_rel_components {
             return Err(TryFromError::ContainsRelComponents);
@@ -132,11 +165,11 @@ impl Deref for AbsolutePathBuf {
 
 impl AbsolutePathBuf {
     pub fn to_absolute_path(&self) -> &AbsolutePath {
-        &*self
+        self
     }
 
     pub fn to_path(&self) -> &std::path::Path {
-        &std::path::Path::new(&self.0)
+        std::path::Path::new(&self.0)
     }
 
     pub fn to_path_buf(&self) -> std::path::PathBuf {
@@ -148,48 +181,39 @@ impl AbsolutePathBuf {
     }
 }
 
-#[inline(always)]
-fn os_str_to_cow_str<'a>(os_str: &'a OsStr) -> Cow<'a, str> {
-    let bytes = os_str.to_bytes();
-    let iter = percent_encode(&bytes, NON_ALPHANUMERIC);
-    // TODO: optimise this again, was buggy before and so we only do Owned for now.
-    Cow::Owned(iter.to_string())
-}
-
 fn file_path<P: AsRef<std::path::Path>>(path: P) -> Result<IriBuf, crate::iri::Error> {
     use std::path::{Component, Prefix};
 
     if !path.as_ref().is_absolute() {
         return Err(crate::iri::Error::NotAbsolute);
     }
-    let input = once(Ok(Cow::Borrowed("file:/")))
-        .chain(path.as_ref().components().filter_map(|c| {
-            Some(Ok(match c {
-                Component::Prefix(prefix) => match prefix.kind() {
-                    Prefix::Verbatim(verbatim) => os_str_to_cow_str(verbatim),
-                    Prefix::DeviceNS(_) => return Some(Err(crate::iri::Error::UnsupportedPrefix)),
-                    Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => Cow::Owned(
-                        format!("{}/{}", os_str_to_cow_str(server), os_str_to_cow_str(share)),
-                    ),
-                    Prefix::Disk(disk) | Prefix::VerbatimDisk(disk) => {
-                        Cow::Owned(format!("/{}:", unsafe {
-                            std::str::from_utf8_unchecked(&[disk])
-                        }))
-                    }
-                },
-                #[cfg(windows)]
-                Component::RootDir => return None,
-                #[cfg(unix)]
-                Component::RootDir => Cow::Borrowed(""),
-                Component::CurDir => return Some(Err(crate::iri::Error::InvalidComponent)),
-                Component::ParentDir => return Some(Err(crate::iri::Error::InvalidComponent)),
-                Component::Normal(value) => os_str_to_cow_str(value),
-            }))
-        }))
-        .collect::<Result<Vec<_>, _>>()?
-        .join("/");
-
-    IriBuf::new(&input).map_err(crate::iri::Error::InvalidIri)
+
+    let mut builder = IriPathBuilder::file();
+
+    for component in path.as_ref().components() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Verbatim(_) | Prefix::DeviceNS(_) => {
+                    return Err(crate::iri::Error::UnsupportedPrefix)
+                }
+                Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                    builder.host(&server.to_bytes())?.push_os_str(share)?;
+                }
+                Prefix::Disk(disk) | Prefix::VerbatimDisk(disk) => {
+                    builder.push(format!("{}:", disk as char))?;
+                }
+            },
+            Component::RootDir => {}
+            Component::CurDir | Component::ParentDir => {
+                return Err(crate::iri::Error::InvalidComponent)
+            }
+            Component::Normal(value) => {
+                builder.push_os_str(value)?;
+            }
+        }
+    }
+
+    builder.build()
 }
 
 pub trait AbsolutePathExt {
@@ -0,0 +1,363 @@
+//! Host-independent models of absolute Windows and POSIX paths.
+//!
+//! `std::path` only understands the conventions of the host it was compiled
+//! for, so a Windows path handled on Linux (or vice versa) can't be converted
+//! to or from a `file:` IRI with it. The types here work on plain strings and
+//! behave the same on every host.
+
+use std::{fmt, str::FromStr};
+
+use iref::IriBuf;
+
+use crate::{
+    iri::{check_file_iri, decode_segment_for, file_iri_host, IriPathBuilder},
+    Platform,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("path not absolute")]
+    NotAbsolute,
+
+    #[error("contains relative components (i.e. '..' or '.')")]
+    ContainsRelComponents,
+
+    #[error("unsupported prefix")]
+    UnsupportedPrefix,
+
+    #[error("invalid character: {0:?}")]
+    InvalidCharacter(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WindowsPrefix {
+    /// A drive letter, such as `C:`.
+    Disk(char),
+
+    /// A network share, such as `\\server\share`.
+    Unc { server: String, share: String },
+}
+
+/// An absolute Windows path, such as `C:\Users\x` or `\\server\share\x`.
+///
+/// Both `\` and `/` are accepted as separators when parsing, as are the
+/// verbatim `\\?\C:\` and `\\?\UNC\` forms.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowsPath {
+    prefix: WindowsPrefix,
+    components: Vec<String>,
+}
+
+impl WindowsPath {
+    pub fn prefix(&self) -> &WindowsPrefix {
+        &self.prefix
+    }
+
+    pub fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    pub fn to_file_iri(&self) -> Result<IriBuf, crate::iri::Error> {
+        let mut builder = IriPathBuilder::file();
+
+        match &self.prefix {
+            WindowsPrefix::Disk(disk) => builder.push(format!("{}:", disk))?,
+            WindowsPrefix::Unc { server, share } => builder.host(server.as_bytes())?.push(share)?,
+        };
+
+        for component in self.components.iter() {
+            builder.push(component)?;
+        }
+
+        builder.build()
+    }
+
+    pub fn from_file_iri(iri: &IriBuf) -> Result<WindowsPath, crate::iri::Error> {
+        check_file_iri(iri)?;
+
+        let host = file_iri_host(iri, true)?;
+        let mut segments = decode_segments(iri, Platform::Windows)?.into_iter();
+        let first = segments.next().ok_or(ParseError::NotAbsolute)?;
+
+        let prefix = match host {
+            Some(server) => {
+                validate_component(&server)?;
+                validate_component(&first)?;
+                WindowsPrefix::Unc {
+                    server,
+                    share: first,
+                }
+            }
+            None => parse_disk(&first).ok_or(ParseError::NotAbsolute)?,
+        };
+
+        let components = segments
+            .map(|x| validate_component(&x).map(|_| x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(WindowsPath { prefix, components })
+    }
+}
+
+impl FromStr for WindowsPath {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(['\\', '/']).collect::<Vec<_>>();
+
+        let (prefix, rest) = match parts.as_slice() {
+            ["", "", "?", "UNC", server, share, rest @ ..] | ["", "", server, share, rest @ ..]
+                if !["?", "."].contains(server) && !server.is_empty() && !share.is_empty() =>
+            {
+                validate_component(server)?;
+                validate_component(share)?;
+                let prefix = WindowsPrefix::Unc {
+                    server: server.to_string(),
+                    share: share.to_string(),
+                };
+                (prefix, rest)
+            }
+            ["", "", "?", disk, rest @ ..] => match parse_disk(disk) {
+                Some(prefix) => (prefix, rest),
+                None => return Err(ParseError::UnsupportedPrefix),
+            },
+            ["", "", ..] => return Err(ParseError::UnsupportedPrefix),
+            // A drive letter must be followed by a separator, or the path is
+            // relative to that drive's current directory.
+            [disk, rest @ ..] if !rest.is_empty() => match parse_disk(disk) {
+                Some(prefix) => (prefix, rest),
+                None => return Err(ParseError::NotAbsolute),
+            },
+            _ => return Err(ParseError::NotAbsolute),
+        };
+
+        let components = rest
+            .iter()
+            .filter(|x| !x.is_empty())
+            .map(|x| validate_component(x).map(|_| x.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(WindowsPath { prefix, components })
+    }
+}
+
+impl fmt::Display for WindowsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.prefix {
+            WindowsPrefix::Disk(disk) => write!(f, "{}:", disk)?,
+            WindowsPrefix::Unc { server, share } => write!(f, r"\\{}\{}", server, share)?,
+        }
+
+        if self.components.is_empty() {
+            return f.write_str(r"\");
+        }
+
+        for component in self.components.iter() {
+            write!(f, r"\{}", component)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An absolute POSIX path, such as `/home/x`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PosixPath {
+    components: Vec<String>,
+}
+
+impl PosixPath {
+    pub fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    pub fn to_file_iri(&self) -> Result<IriBuf, crate::iri::Error> {
+        let mut builder = IriPathBuilder::file();
+
+        for component in self.components.iter() {
+            builder.push(component)?;
+        }
+
+        builder.build()
+    }
+
+    pub fn from_file_iri(iri: &IriBuf) -> Result<PosixPath, crate::iri::Error> {
+        check_file_iri(iri)?;
+        file_iri_host(iri, false)?;
+
+        Ok(PosixPath {
+            components: decode_segments(iri, Platform::Linux)?,
+        })
+    }
+}
+
+impl FromStr for PosixPath {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with('/') {
+            return Err(ParseError::NotAbsolute);
+        }
+
+        let components = s
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                if x == "." || x == ".." {
+                    Err(ParseError::ContainsRelComponents)
+                } else if x.contains('\0') {
+                    Err(ParseError::InvalidCharacter('\0'))
+                } else {
+                    Ok(x.to_string())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PosixPath { components })
+    }
+}
+
+impl fmt::Display for PosixPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.components.is_empty() {
+            return f.write_str("/");
+        }
+
+        for component in self.components.iter() {
+            write!(f, "/{}", component)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub trait ForeignPathExt {
+    /// Converts a path string written for the given platform into a `file:`
+    /// IRI, regardless of the host operating system.
+    fn to_file_iri_for(&self, platform: Platform) -> Result<IriBuf, crate::iri::Error>;
+}
+
+impl ForeignPathExt for str {
+    fn to_file_iri_for(&self, platform: Platform) -> Result<IriBuf, crate::iri::Error> {
+        if platform.is_windows() {
+            self.parse::<WindowsPath>()?.to_file_iri()
+        } else {
+            self.parse::<PosixPath>()?.to_file_iri()
+        }
+    }
+}
+
+#[inline]
+fn parse_disk(input: &str) -> Option<WindowsPrefix> {
+    let mut chars = input.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(disk), Some(':'), None) if disk.is_ascii_alphabetic() => {
+            Some(WindowsPrefix::Disk(disk))
+        }
+        _ => None,
+    }
+}
+
+fn validate_component(input: &str) -> Result<(), ParseError> {
+    if input == "." || input == ".." {
+        return Err(ParseError::ContainsRelComponents);
+    }
+
+    match input
+        .chars()
+        .find(|c| (*c as u32) < 0x20 || r#"<>:"|?*"#.contains(*c))
+    {
+        Some(c) => Err(ParseError::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+fn decode_segments(iri: &IriBuf, platform: Platform) -> Result<Vec<String>, crate::iri::Error> {
+    iri.path()
+        .into_iter()
+        .filter(|x| !x.is_empty())
+        .map(|x| decode_segment_for(x.as_str(), platform))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iri::IriBufExt;
+
+    #[test]
+    fn windows_path_to_iri() {
+        let iri = r"C:\Users\x\My Documents".to_file_iri_for(Platform::Windows);
+        assert_eq!(iri.unwrap().as_str(), "file:///C:/Users/x/My%20Documents");
+
+        let iri = r"\\server\share\dir".to_file_iri_for(Platform::Windows);
+        assert_eq!(iri.unwrap().as_str(), "file://server/share/dir");
+
+        let iri = r"\\?\UNC\server\share\dir".to_file_iri_for(Platform::Windows);
+        assert_eq!(iri.unwrap().as_str(), "file://server/share/dir");
+
+        let iri = r"\\?\C:\x".to_file_iri_for(Platform::Windows);
+        assert_eq!(iri.unwrap().as_str(), "file:///C:/x");
+    }
+
+    #[test]
+    fn windows_path_parse_errors() {
+        assert_eq!(
+            "C:relative".parse::<WindowsPath>(),
+            Err(ParseError::NotAbsolute)
+        );
+        assert_eq!(
+            r"\rooted".parse::<WindowsPath>(),
+            Err(ParseError::NotAbsolute)
+        );
+        assert_eq!(
+            r"\\.\COM1".parse::<WindowsPath>(),
+            Err(ParseError::UnsupportedPrefix)
+        );
+        assert_eq!(
+            r"C:\a\..\b".parse::<WindowsPath>(),
+            Err(ParseError::ContainsRelComponents)
+        );
+        assert_eq!(
+            r"C:\a\b:c".parse::<WindowsPath>(),
+            Err(ParseError::InvalidCharacter(':'))
+        );
+    }
+
+    #[test]
+    fn iri_to_windows_path() {
+        let iri = IriBuf::new("file:///C:/Users/x/My%20Documents").unwrap();
+        assert_eq!(
+            iri.to_path_string_for(Platform::Windows).unwrap(),
+            r"C:\Users\x\My Documents"
+        );
+
+        let iri = IriBuf::new("file://server/share/dir").unwrap();
+        assert_eq!(
+            iri.to_path_string_for(Platform::Windows).unwrap(),
+            r"\\server\share\dir"
+        );
+
+        let iri = IriBuf::new("file:///C:/a%5Cb").unwrap();
+        assert!(iri.to_path_string_for(Platform::Windows).is_err());
+    }
+
+    #[test]
+    fn posix_round_trip() {
+        let iri = "/home/x/a b".to_file_iri_for(Platform::Linux).unwrap();
+        assert_eq!(iri.as_str(), "file:///home/x/a%20b");
+        assert_eq!(
+            iri.to_path_string_for(Platform::MacOS).unwrap(),
+            "/home/x/a b"
+        );
+
+        // A Windows IRI decodes as an ordinary POSIX path, not as a drive.
+        let iri = IriBuf::new("file:///C:/x").unwrap();
+        assert_eq!(iri.to_path_string_for(Platform::Linux).unwrap(), "/C:/x");
+
+        let iri = IriBuf::new("file://server/share").unwrap();
+        assert!(iri.to_path_string_for(Platform::Linux).is_err());
+
+        assert!("relative/path".to_file_iri_for(Platform::Linux).is_err());
+    }
+}
@@ -1,4 +1,6 @@
 pub mod absolute;
+pub mod foreign;
+pub mod portable;
 
 #[cfg(windows)]
 pub const PATH_SEP_STR: &str = r"\";
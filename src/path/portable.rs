@@ -0,0 +1,189 @@
+//! File names that are valid on Windows, macOS and Linux alike.
+//!
+//! Each platform rejects different names: Windows reserves device names such
+//! as `CON` and `aux.txt`, a set of punctuation and trailing dots or spaces,
+//! macOS treats `:` as a separator in some APIs, and all three limit a name's
+//! length. The rules here are the union of them, checked as plain string
+//! logic so that they give the same answer on every host.
+
+/// The longest name, in bytes, every supported file system accepts.
+pub const MAX_FILE_NAME_BYTES: usize = 255;
+
+const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+const RESERVED_NUMBERED: &[&str] = &["COM", "LPT"];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PortableNameError {
+    #[error("name is empty")]
+    Empty,
+
+    #[error("name is {0} bytes long; the limit is 255")]
+    TooLong(usize),
+
+    #[error("name is reserved: {0:?}")]
+    Reserved(String),
+
+    #[error("invalid character: {0:?}")]
+    InvalidCharacter(char),
+
+    #[error("name ends with a dot or space")]
+    TrailingDotOrSpace,
+}
+
+/// Checks that `name` is a single file name that is valid on every
+/// platform.
+pub fn check_portable_file_name(name: &str) -> Result<(), PortableNameError> {
+    if name.is_empty() {
+        return Err(PortableNameError::Empty);
+    }
+
+    if name.len() > MAX_FILE_NAME_BYTES {
+        return Err(PortableNameError::TooLong(name.len()));
+    }
+
+    if let Some(c) = name.chars().find(|c| !is_portable_char(*c)) {
+        return Err(PortableNameError::InvalidCharacter(c));
+    }
+
+    if name == "." || name == ".." || is_reserved(name) {
+        return Err(PortableNameError::Reserved(name.to_string()));
+    }
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(PortableNameError::TrailingDotOrSpace);
+    }
+
+    Ok(())
+}
+
+/// Maps any string, such as a URL or a document title, to a file name that
+/// passes `check_portable_file_name`, of at most `MAX_FILE_NAME_BYTES`.
+pub fn portable_file_name(name: &str) -> String {
+    portable_file_name_within(name, MAX_FILE_NAME_BYTES)
+}
+
+/// `portable_file_name`, limited to `max_bytes` (at most
+/// `MAX_FILE_NAME_BYTES`).
+///
+/// Runs of invalid characters and whitespace become a single `-`, separators
+/// at either end are dropped, reserved names get a trailing `_`, and the
+/// result is cut at a character boundary. An empty result becomes `_`.
+pub fn portable_file_name_within(name: &str, max_bytes: usize) -> String {
+    let max_bytes = max_bytes.clamp(1, MAX_FILE_NAME_BYTES);
+
+    let mut out = String::with_capacity(name.len().min(max_bytes));
+    for c in name.chars() {
+        if is_portable_char(c) && !c.is_whitespace() || c == ' ' {
+            out.push(c);
+        } else if !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+
+    let mut out = trim(&out).to_string();
+    if out.is_empty() || out.chars().all(|c| c == '.') {
+        out = "_".to_string();
+    }
+
+    if out.len() > max_bytes {
+        let mut end = max_bytes;
+        while !out.is_char_boundary(end) {
+            end -= 1;
+        }
+        out = trim(&out[..end]).to_string();
+        if out.is_empty() {
+            out = "_".to_string();
+        }
+    }
+
+    if is_reserved(&out) {
+        if out.len() >= max_bytes {
+            out.pop();
+            out.truncate(trim(&out).len());
+        }
+        let stem = out.find('.').unwrap_or(out.len());
+        out.insert(stem, '_');
+    }
+
+    out
+}
+
+fn is_portable_char(c: char) -> bool {
+    !c.is_control() && !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+}
+
+fn trim(name: &str) -> &str {
+    name.trim_matches(['-', ' ']).trim_end_matches(['.', ' '])
+}
+
+/// Windows' device names, which are reserved with any extension and in any
+/// case: `CON`, `con.txt` and `Aux .tar.gz` all open a device.
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+
+    if RESERVED.iter().any(|x| stem.eq_ignore_ascii_case(x)) {
+        return true;
+    }
+
+    let mut chars = stem.chars();
+    let prefix: String = chars.by_ref().take(3).collect();
+    let digits: String = chars.collect();
+
+    RESERVED_NUMBERED
+        .iter()
+        .any(|x| prefix.eq_ignore_ascii_case(x))
+        && matches!(
+            &*digits,
+            "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "¹" | "²" | "³"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        assert_eq!(check_portable_file_name("Bad App"), Ok(()));
+        assert_eq!(check_portable_file_name(".config"), Ok(()));
+        assert_eq!(check_portable_file_name("console"), Ok(()));
+        assert_eq!(check_portable_file_name(""), Err(PortableNameError::Empty));
+        assert_eq!(
+            check_portable_file_name("aux.txt"),
+            Err(PortableNameError::Reserved("aux.txt".into()))
+        );
+        assert_eq!(
+            check_portable_file_name("COM1"),
+            Err(PortableNameError::Reserved("COM1".into()))
+        );
+        assert_eq!(
+            check_portable_file_name("a:b"),
+            Err(PortableNameError::InvalidCharacter(':'))
+        );
+        assert_eq!(
+            check_portable_file_name("app."),
+            Err(PortableNameError::TrailingDotOrSpace)
+        );
+        assert_eq!(
+            check_portable_file_name(&"x".repeat(256)),
+            Err(PortableNameError::TooLong(256))
+        );
+    }
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(
+            portable_file_name("https://example.com/a?b=c"),
+            "https-example.com-a-b=c"
+        );
+        assert_eq!(portable_file_name("  My Document. "), "My Document");
+        assert_eq!(portable_file_name("con.txt"), "con_.txt");
+        assert_eq!(portable_file_name("..."), "_");
+        assert_eq!(portable_file_name("tab\there"), "tab-here");
+        assert_eq!(portable_file_name_within("ééé", 5), "éé");
+
+        for name in &["CON", "a/b\\c", "\u{0}", &"€".repeat(200)] {
+            assert_eq!(check_portable_file_name(&portable_file_name(name)), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+/// An operating system with its own conventions for paths and directories.
+///
+/// Unlike the `cfg`-selected modules, a `Platform` is an ordinary value, so
+/// code running on one host can reason about the paths of another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    Linux,
+    MacOS,
+    Windows,
+    Ios,
+    Android,
+}
+
+impl Platform {
+    /// The platform this crate was compiled for.
+    #[inline]
+    pub fn current() -> Platform {
+        if cfg!(windows) {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOS
+        } else if cfg!(target_os = "ios") {
+            Platform::Ios
+        } else if cfg!(target_os = "android") {
+            Platform::Android
+        } else {
+            Platform::Linux
+        }
+    }
+
+    /// Whether the platform uses Windows path syntax (drive letters, UNC
+    /// shares and `\` separators) rather than POSIX path syntax.
+    #[inline]
+    pub fn is_windows(self) -> bool {
+        self == Platform::Windows
+    }
+
+    /// The conventional `AppDirs` prefix for a project, given as a reverse
+    /// domain qualifier (e.g. `com`), an organization and an application:
+    ///
+    /// - macOS: a bundle identifier, `com.Foo-Corp.Bar-App`
+    /// - Windows: `Foo Corp\Bar App`
+    /// - Linux, iOS and Android: `barapp`, as apps on iOS and Android are
+    ///   already inside their own container
+    ///
+    /// Empty parts are left out.
+    pub fn project_prefix(self, qualifier: &str, organization: &str, application: &str) -> PathBuf {
+        match self {
+            Platform::MacOS => {
+                let parts = [qualifier, organization, application]
+                    .iter()
+                    .map(|x| x.trim().replace(char::is_whitespace, "-"))
+                    .filter(|x| !x.is_empty())
+                    .collect::<Vec<_>>();
+                PathBuf::from(parts.join("."))
+            }
+            Platform::Windows => [organization, application]
+                .iter()
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .collect(),
+            Platform::Linux | Platform::Ios | Platform::Android => PathBuf::from(
+                application
+                    .chars()
+                    .filter(|x| !x.is_whitespace())
+                    .collect::<String>()
+                    .to_lowercase(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_prefix() {
+        let prefix = |platform: Platform| platform.project_prefix("com", "Foo Corp", "Bar App");
+
+        assert_eq!(
+            prefix(Platform::MacOS),
+            PathBuf::from("com.Foo-Corp.Bar-App")
+        );
+        assert_eq!(
+            prefix(Platform::Windows),
+            PathBuf::from("Foo Corp").join("Bar App")
+        );
+        assert_eq!(prefix(Platform::Linux), PathBuf::from("barapp"));
+        assert_eq!(
+            Platform::MacOS.project_prefix("", "", "Bar App"),
+            PathBuf::from("Bar-App")
+        );
+    }
+}
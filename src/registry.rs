@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{AppDirs, Error};
+
+/// A thread-safe cache of `AppDirs` by prefix, so that each prefix's
+/// directories are laid out and created once rather than on every call.
+///
+/// Directories removed from disk after they were cached are not recreated
+/// until the prefix is invalidated.
+pub struct Registry {
+    layout: fn(&Path) -> Result<AppDirs, Error>,
+    entries: Mutex<Entries>,
+}
+
+/// The cached directories, and how many times they were cleared, so that
+/// `get` can tell whether what it laid out is still current.
+#[derive(Default)]
+struct Entries {
+    generation: u64,
+    dirs: HashMap<PathBuf, Arc<AppDirs>>,
+}
+
+impl Registry {
+    /// A registry whose directories are laid out by `layout`, such as
+    /// `pathos::user::app_layout`.
+    pub fn new(layout: fn(&Path) -> Result<AppDirs, Error>) -> Registry {
+        Registry {
+            layout,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// The directories for `prefix`, laid out and created on first use.
+    /// Failures aren't cached.
+    ///
+    /// The registry isn't locked while directories are created, so a slow
+    /// file system for one prefix doesn't hold up the others. Two threads
+    /// asking for a new prefix at once may both create it; the first to
+    /// finish is kept. Directories laid out before a concurrent `clear` are
+    /// discarded and laid out again.
+    pub fn get<P: Into<PathBuf>>(&self, prefix: P) -> Result<Arc<AppDirs>, Error> {
+        let prefix = prefix.into();
+
+        loop {
+            let generation = {
+                let entries = self.entries.lock().unwrap();
+                if let Some(dirs) = entries.dirs.get(&prefix) {
+                    return Ok(Arc::clone(dirs));
+                }
+                entries.generation
+            };
+
+            let dirs = (self.layout)(&prefix)?;
+            dirs.create()?;
+
+            let mut entries = self.entries.lock().unwrap();
+            if entries.generation == generation {
+                return Ok(Arc::clone(
+                    entries.dirs.entry(prefix).or_insert_with(|| Arc::new(dirs)),
+                ));
+            }
+        }
+    }
+
+    /// Forgets the directories for `prefix`, so that the next `get` lays out
+    /// and creates them again. Returns whether the prefix was cached.
+    pub fn invalidate<P: AsRef<Path>>(&self, prefix: P) -> bool {
+        self.entries
+            .lock()
+            .unwrap()
+            .dirs
+            .remove(prefix.as_ref())
+            .is_some()
+    }
+
+    /// Forgets every prefix.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        entries.dirs.clear();
+    }
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.entries.lock().unwrap().dirs.keys())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    fn root() -> PathBuf {
+        std::env::temp_dir().join(format!("pathos-registry-{}", std::process::id()))
+    }
+
+    fn counted(prefix: &Path) -> Result<AppDirs, Error> {
+        CREATED.fetch_add(1, Ordering::SeqCst);
+        let root = root().join(prefix);
+        Ok(AppDirs::new(
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root,
+        ))
+    }
+
+    #[test]
+    fn caches_by_prefix() {
+        let registry = Registry::new(counted);
+
+        let a = registry.get("a").unwrap();
+        assert!(Arc::ptr_eq(&a, &registry.get("a").unwrap()));
+        registry.get("b").unwrap();
+        assert_eq!(CREATED.load(Ordering::SeqCst), 2);
+
+        assert!(registry.invalidate("a"));
+        assert!(!registry.invalidate("a"));
+        assert!(!Arc::ptr_eq(&a, &registry.get("a").unwrap()));
+        assert_eq!(CREATED.load(Ordering::SeqCst), 3);
+
+        registry.clear();
+        registry.get("b").unwrap();
+        assert_eq!(CREATED.load(Ordering::SeqCst), 4);
+
+        std::fs::remove_dir_all(root()).unwrap();
+    }
+
+    static CLEARING: once_cell::sync::Lazy<Registry> =
+        once_cell::sync::Lazy::new(|| Registry::new(clearing));
+    static CLEARED: AtomicUsize = AtomicUsize::new(0);
+
+    fn clearing(prefix: &Path) -> Result<AppDirs, Error> {
+        // Stands in for a `reload` that happens while the first layout is
+        // still being created.
+        if CLEARED.fetch_add(1, Ordering::SeqCst) == 0 {
+            CLEARING.clear();
+        }
+
+        let root = std::env::temp_dir()
+            .join(format!("pathos-registry-clearing-{}", std::process::id()))
+            .join(prefix);
+        Ok(AppDirs::new(
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root,
+        ))
+    }
+
+    #[test]
+    fn discards_layout_from_before_clear() {
+        let dirs = CLEARING.get("clearing").unwrap();
+        assert_eq!(CLEARED.load(Ordering::SeqCst), 2);
+        assert!(Arc::ptr_eq(&dirs, &CLEARING.get("clearing").unwrap()));
+        std::fs::remove_dir_all(dirs.data_dir().parent().unwrap()).unwrap();
+    }
+
+    static REENTRANT: once_cell::sync::Lazy<Registry> =
+        once_cell::sync::Lazy::new(|| Registry::new(reentrant));
+
+    fn reentrant(prefix: &Path) -> Result<AppDirs, Error> {
+        // Would deadlock if the registry were locked during layout.
+        REENTRANT.invalidate("other");
+
+        let root = std::env::temp_dir()
+            .join(format!("pathos-registry-reentrant-{}", std::process::id()))
+            .join(prefix);
+        Ok(AppDirs::new(
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root.clone(),
+            root,
+        ))
+    }
+
+    #[test]
+    fn layout_may_use_registry() {
+        let dirs = REENTRANT.get("reentrant").unwrap();
+        std::fs::remove_dir_all(dirs.data_dir().parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::{Mutex, RwLock};
+
+use crate::Error;
+
+/// A lazily resolved value that can be resolved again with `reload`, e.g.
+/// after a long-running process changes `XDG_CONFIG_HOME`.
+///
+/// Errors are never cached: if resolution fails, the next `get` tries again.
+///
+/// Values are handed out as `&'static T` so that paths borrowed from them
+/// stay valid after a reload. Each distinct value is therefore kept for the
+/// life of the process, but values are interned: reloading to any value seen
+/// before, such as when a setting is toggled back and forth, reuses it. Memory
+/// grows only with the number of distinct values, not with reloads.
+pub struct Resolver<T: 'static> {
+    resolve: fn() -> Result<T, Error>,
+    value: RwLock<Option<&'static T>>,
+    seen: Mutex<Vec<&'static T>>,
+}
+
+impl<T: PartialEq + Send + Sync + 'static> Resolver<T> {
+    pub const fn new(resolve: fn() -> Result<T, Error>) -> Resolver<T> {
+        Resolver {
+            resolve,
+            value: RwLock::new(None),
+            seen: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The current value, resolving it first if it hasn't been yet (or if
+    /// every previous attempt failed).
+    pub fn get(&self) -> Result<&'static T, Error> {
+        if let Some(value) = *self.value.read().unwrap() {
+            return Ok(value);
+        }
+
+        let mut guard = self.value.write().unwrap();
+        if let Some(value) = *guard {
+            return Ok(value);
+        }
+
+        let value = self.intern((self.resolve)()?);
+        *guard = Some(value);
+        Ok(value)
+    }
+
+    /// Resolves the value again. On failure the previous value, if any, is
+    /// kept and the error returned.
+    ///
+    /// A value not seen before is kept for the life of the process; see the
+    /// type's documentation.
+    pub fn reload(&self) -> Result<&'static T, Error> {
+        let value = self.intern((self.resolve)()?);
+        *self.value.write().unwrap() = Some(value);
+        Ok(value)
+    }
+
+    /// The previously seen value equal to `value`, or `value` itself, leaked.
+    fn intern(&self, value: T) -> &'static T {
+        let mut seen = self.seen.lock().unwrap();
+
+        if let Some(old) = seen.iter().find(|x| ***x == value) {
+            return old;
+        }
+
+        let value: &'static T = Box::leak(Box::new(value));
+        seen.push(value);
+        value
+    }
+}
+
+impl<T> std::fmt::Debug for Resolver<T>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolver")
+            .field("value", &*self.value.read().unwrap())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn flaky() -> Result<usize, Error> {
+        match CALLS.fetch_add(1, Ordering::SeqCst) {
+            0 => Err(Error::NotFound("Test")),
+            n => Ok(n / 3),
+        }
+    }
+
+    #[test]
+    fn retries_and_reloads() {
+        let resolver = Resolver::new(flaky);
+
+        // The first failure isn't cached.
+        assert!(resolver.get().is_err());
+        let first = resolver.get().unwrap();
+        assert_eq!(*first, 0);
+        assert!(std::ptr::eq(first, resolver.get().unwrap()));
+
+        // An unchanged value is reused rather than leaked again.
+        let same = resolver.reload().unwrap();
+        assert!(std::ptr::eq(first, same));
+
+        let changed = resolver.reload().unwrap();
+        assert_eq!(*changed, 1);
+        assert_eq!(*first, 0);
+    }
+
+    static TOGGLES: AtomicUsize = AtomicUsize::new(0);
+
+    fn toggle() -> Result<usize, Error> {
+        Ok(TOGGLES.fetch_add(1, Ordering::SeqCst) % 2)
+    }
+
+    #[test]
+    fn interns_values() {
+        let resolver = Resolver::new(toggle);
+
+        let a = resolver.get().unwrap();
+        let b = resolver.reload().unwrap();
+        assert_ne!(a, b);
+
+        // Toggling back reuses the values seen before instead of leaking more.
+        for _ in 0..4 {
+            let x = resolver.reload().unwrap();
+            assert!(std::ptr::eq(x, a) || std::ptr::eq(x, b));
+        }
+        assert_eq!(resolver.seen.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::{diagnose::Status, AppDirs, Error};
+
+/// Whether an app is installed for the current user or for every user, so
+/// that the choice can be made at runtime. Both scopes give the same
+/// `AppDirs`, from the current `Backend`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Scope {
+    User,
+    System,
+}
+
+impl Scope {
+    /// A lowercase name, `"user"` or `"system"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::User => "user",
+            Scope::System => "system",
+        }
+    }
+
+    /// `System` when running with root privileges (an effective uid of 0),
+    /// otherwise `User`.
+    ///
+    /// Privileges are only checked on Unix. Elsewhere this is always `User`,
+    /// even for an elevated process or a Windows service running as
+    /// `LocalSystem`, which should choose the scope explicitly instead, such
+    /// as from a command line flag parsed with `str::parse`.
+    pub fn detect() -> Scope {
+        #[cfg(unix)]
+        {
+            if unsafe { libc::geteuid() } == 0 {
+                return Scope::System;
+            }
+        }
+
+        Scope::User
+    }
+
+    /// The directories for `prefix` in this scope, without creating them.
+    pub fn app_layout<P: AsRef<Path>>(self, prefix: P) -> Result<AppDirs, Error> {
+        match self {
+            Scope::User => crate::backend().app_layout(prefix.as_ref()),
+            Scope::System => crate::backend().system_app_layout(prefix.as_ref()),
+        }
+    }
+
+    /// The directories for `prefix` in this scope, created if missing.
+    pub fn app_dirs<P: AsRef<Path>>(self, prefix: P) -> Result<AppDirs, Error> {
+        let dirs = self.app_layout(prefix)?;
+        dirs.create()?;
+        Ok(dirs)
+    }
+
+    /// `app_dirs`, except that if this is `System` and the system
+    /// directories can't be created or aren't all writable, the `User`
+    /// directories are used instead. Returns the scope that was used.
+    pub fn app_dirs_with_fallback<P: AsRef<Path>>(
+        self,
+        prefix: P,
+    ) -> Result<(Scope, AppDirs), Error> {
+        let prefix = prefix.as_ref();
+
+        if self == Scope::System {
+            let writable = |dirs: &AppDirs| dirs.iter().all(|(_, x)| Status::inspect(x).writable);
+
+            match Scope::System.app_dirs(prefix) {
+                Ok(dirs) if writable(&dirs) => return Ok((Scope::System, dirs)),
+                Ok(_) => log::debug!("System directories for {:?} aren't writable", prefix),
+                Err(e) => log::debug!("System directories for {:?}: {}", prefix, e),
+            }
+        }
+
+        Scope::User.app_dirs(prefix).map(|dirs| (Scope::User, dirs))
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown scope: '{0}'")]
+pub struct UnknownScope(pub String);
+
+impl FromStr for Scope {
+    type Err = UnknownScope;
+
+    /// Parses the names returned by `as_str`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Scope::User, Scope::System]
+            .iter()
+            .copied()
+            .find(|x| x.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownScope(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for scope in &[Scope::User, Scope::System] {
+            assert_eq!(scope.to_string().parse::<Scope>(), Ok(*scope));
+        }
+        assert!("machine".parse::<Scope>().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Where an app keeps its per-user directories, chosen at runtime rather
+//! than by the platform `pathos` was compiled for.
+//!
+//! Developer tools often want `~/.config/<app>` on macOS and Windows as well
+//! as Linux, or a single `~/.<app>`. Both `Strategy` and `PreferExisting`
+//! implement `Backend`, so either can be installed with `set_backend` to
+//! change what `AppDirs::user` returns.
+
+use std::path::Path;
+
+use crate::{
+    backend::{Backend, Native},
+    diagnose::Provenance,
+    layout::UserLayout,
+    AppDirs, DirKind, Error,
+};
+
+/// A way of laying out an app's per-user directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// The built-in layout for the target, e.g.
+    /// `~/Library/Application Support/<app>` on macOS.
+    Native,
+    /// The XDG Base Directory layout on every platform, e.g.
+    /// `~/.config/<app>`; see `pathos::xdg::app_layout`.
+    Xdg,
+    /// Everything under `~/.<app>`, with `cache`, `log` and `tmp`
+    /// subdirectories.
+    Dotfile,
+}
+
+impl Strategy {
+    /// The current user's directories for `prefix`, without creating them.
+    pub fn app_layout<P: AsRef<Path>>(self, prefix: P) -> Result<AppDirs, Error> {
+        let prefix = prefix.as_ref();
+
+        match self {
+            Strategy::Native => Native.app_layout(prefix),
+            Strategy::Xdg => crate::xdg::app_layout(prefix),
+            Strategy::Dotfile => {
+                let home = Native.user_layout()?.home_dir().to_path_buf();
+                Ok(Strategy::dotfile_layout(&home, prefix))
+            }
+        }
+    }
+
+    /// The `Dotfile` layout for `prefix` under `home`.
+    pub fn dotfile_layout<P: AsRef<Path>>(home: &Path, prefix: P) -> AppDirs {
+        let mut name = std::ffi::OsString::from(".");
+        name.push(prefix.as_ref());
+        let root = home.join(name);
+
+        AppDirs::new(
+            root.clone(),
+            root.clone(),
+            root.join("cache"),
+            root.join("log"),
+            root.clone(),
+            root.join("tmp"),
+        )
+    }
+}
+
+impl Backend for Strategy {
+    fn user_layout(&self) -> Result<UserLayout, Error> {
+        Native.user_layout()
+    }
+
+    fn app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        Strategy::app_layout(*self, prefix)
+    }
+
+    fn system_app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        Native.system_app_layout(prefix)
+    }
+
+    fn provenance(&self, kind: DirKind) -> Provenance {
+        Native.provenance(kind)
+    }
+}
+
+/// A preferred `Strategy`, unless the app's directories already exist in a
+/// legacy location, so that existing users keep finding their files where
+/// they left them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreferExisting {
+    preferred: Strategy,
+    legacy: Vec<Strategy>,
+}
+
+impl PreferExisting {
+    pub fn new(preferred: Strategy) -> PreferExisting {
+        PreferExisting {
+            preferred,
+            legacy: vec![],
+        }
+    }
+
+    /// Adds a legacy location, checked after any added before it.
+    pub fn legacy(&mut self, strategy: Strategy) -> &mut Self {
+        self.legacy.push(strategy);
+        self
+    }
+
+    /// The directories of the first legacy strategy whose config or data
+    /// directory exists, otherwise those of the preferred strategy, along
+    /// with the strategy that was chosen.
+    pub fn app_layout<P: AsRef<Path>>(&self, prefix: P) -> Result<(Strategy, AppDirs), Error> {
+        let prefix = prefix.as_ref();
+
+        for strategy in &self.legacy {
+            let dirs = match strategy.app_layout(prefix) {
+                Ok(dirs) => dirs,
+                Err(_) => continue,
+            };
+
+            if dirs.config_dir().is_dir() || dirs.data_dir().is_dir() {
+                return Ok((*strategy, dirs));
+            }
+        }
+
+        self.preferred
+            .app_layout(prefix)
+            .map(|dirs| (self.preferred, dirs))
+    }
+}
+
+impl Backend for PreferExisting {
+    fn user_layout(&self) -> Result<UserLayout, Error> {
+        Native.user_layout()
+    }
+
+    fn app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        PreferExisting::app_layout(self, prefix).map(|(_, dirs)| dirs)
+    }
+
+    fn system_app_layout(&self, prefix: &Path) -> Result<AppDirs, Error> {
+        Native.system_app_layout(prefix)
+    }
+
+    fn provenance(&self, kind: DirKind) -> Provenance {
+        Native.provenance(kind)
+    }
+}
+
+impl From<Strategy> for PreferExisting {
+    fn from(preferred: Strategy) -> Self {
+        PreferExisting::new(preferred)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotfile() {
+        let dirs = Strategy::dotfile_layout(Path::new("/home/x"), "app");
+        assert_eq!(dirs.config_dir(), Path::new("/home/x/.app"));
+        assert_eq!(dirs.cache_dir(), Path::new("/home/x/.app/cache"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Directories of a system image that is being built somewhere else on the
+//! host, such as a `DESTDIR` staging tree or a root filesystem under
+//! `/build/rootfs`.
+//!
+//! Every path is held as a `Rooted` pair: `target()` is the path as the
+//! image will see it once booted (`/etc/app`), and `host()` is where it is
+//! on the build host (`/build/rootfs/etc/app`). Only the host path should be
+//! passed to filesystem operations, and only the target path should be
+//! written into the image's configuration.
+
+use std::{
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{AppDirs, DirKind, Error};
+
+/// How many symlinks `Sysroot::path` follows before giving up, as Linux does.
+const MAX_LINKS: usize = 40;
+
+/// The directory a system image is mounted at on the build host.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sysroot {
+    root: PathBuf,
+}
+
+impl Sysroot {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Sysroot {
+        Sysroot { root: root.into() }
+    }
+
+    /// The sysroot itself, on the host.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Pairs a path as the image sees it with where it is on the host.
+    ///
+    /// `..` can't climb above the sysroot, and symlinks that already exist
+    /// in the image are followed within it, as they would be once it's
+    /// booted: with `var/run -> /run`, `/var/run/app` is found under the
+    /// sysroot's `run`, not the host's. Links are followed at most 40
+    /// times, after which the rest of the path is left as it is;
+    /// `RootedAppDirs::create` refuses to create directories through such a
+    /// link.
+    pub fn path<P: Into<PathBuf>>(&self, target: P) -> Rooted {
+        let target = target.into();
+        let mut host = self.root.clone();
+        let depth = host.components().count();
+
+        let mut pending = segments(&target);
+        let mut links = 0;
+
+        while let Some(part) = pending.pop() {
+            if part == ".." {
+                if host.components().count() > depth {
+                    host.pop();
+                }
+                continue;
+            }
+
+            host.push(&part);
+
+            if links == MAX_LINKS {
+                continue;
+            }
+
+            if let Ok(link) = std::fs::read_link(&host) {
+                links += 1;
+                host.pop();
+                if link.has_root() {
+                    host = self.root.clone();
+                }
+                pending.extend(segments(&link));
+            }
+        }
+
+        Rooted { target, host }
+    }
+
+    /// A symlink left in `host`, below the sysroot, after `path` gave up
+    /// following links.
+    fn unresolved_link<'a>(&self, host: &'a Path) -> Option<&'a Path> {
+        host.ancestors()
+            .take_while(|x| *x != self.root && x.starts_with(&self.root))
+            .find(|x| {
+                x.symlink_metadata()
+                    .map(|x| x.file_type().is_symlink())
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Places an app's directories, as laid out for the image, under the
+    /// sysroot.
+    pub fn app_dirs(&self, target: AppDirs) -> RootedAppDirs {
+        RootedAppDirs {
+            sysroot: self.clone(),
+            target,
+        }
+    }
+}
+
+/// The names and `..`s in `path`, last first, so that they can be popped in
+/// order.
+fn segments(path: &Path) -> Vec<OsString> {
+    let mut segments = path
+        .components()
+        .filter_map(|x| match x {
+            Component::Normal(part) => Some(part.to_os_string()),
+            Component::ParentDir => Some("..".into()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    segments.reverse();
+    segments
+}
+
+/// One path as both the image and the build host see it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rooted {
+    target: PathBuf,
+    host: PathBuf,
+}
+
+impl Rooted {
+    /// The path as the image sees it, e.g. `/etc/app`.
+    #[inline]
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// The path on the build host, under the sysroot, e.g.
+    /// `/build/rootfs/etc/app`.
+    #[inline]
+    pub fn host(&self) -> &Path {
+        &self.host
+    }
+}
+
+/// An app's directories within a system image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RootedAppDirs {
+    sysroot: Sysroot,
+    target: AppDirs,
+}
+
+impl RootedAppDirs {
+    #[inline]
+    pub fn sysroot(&self) -> &Sysroot {
+        &self.sysroot
+    }
+
+    /// The directories as the image sees them.
+    #[inline]
+    pub fn target(&self) -> &AppDirs {
+        &self.target
+    }
+
+    /// The directory of the given kind, or `None` for kinds an app doesn't
+    /// have.
+    pub fn dir(&self, kind: DirKind) -> Option<Rooted> {
+        self.target.dir(kind).map(|x| self.sysroot.path(x))
+    }
+
+    /// Every directory, in the order of `DirKind::APP`.
+    pub fn iter(&self) -> impl Iterator<Item = (DirKind, Rooted)> + '_ {
+        self.target
+            .iter()
+            .map(move |(kind, dir)| (kind, self.sysroot.path(dir)))
+    }
+
+    /// Creates every directory under the sysroot, including any missing
+    /// parents. Symlinks in the image are followed within it, and a link
+    /// that `Sysroot::path` couldn't resolve is an error rather than being
+    /// followed on the host.
+    pub fn create(&self) -> Result<(), Error> {
+        for (_, dir) in self.iter() {
+            if let Some(link) = self.sysroot.unresolved_link(dir.host()) {
+                let e = std::io::Error::other(format!(
+                    "too many levels of symbolic links: {}",
+                    link.display()
+                ));
+                return Err(Error::CreateDirectoryFailed(
+                    eieio::Error::from(e),
+                    dir.host().to_path_buf(),
+                ));
+            }
+
+            std::fs::create_dir_all(dir.host()).map_err(|e| {
+                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.host().to_path_buf())
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Platform;
+
+    #[test]
+    fn target_and_host() {
+        let sysroot = Sysroot::new("/build/rootfs");
+
+        let etc = sysroot.path("/etc/app");
+        assert_eq!(etc.target(), Path::new("/etc/app"));
+        assert_eq!(etc.host(), Path::new("/build/rootfs/etc/app"));
+
+        let escape = sysroot.path("/../../etc/../../passwd");
+        assert_eq!(escape.host(), Path::new("/build/rootfs/passwd"));
+
+        let dirs = sysroot.app_dirs(Platform::Linux.system_app_layout(None, "app").unwrap());
+        assert_eq!(dirs.target().log_dir(), Path::new("/var/log/app"));
+        assert_eq!(
+            dirs.dir(DirKind::Log).unwrap().host(),
+            Path::new("/build/rootfs/var/log/app")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_stay_inside() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("pathos-sysroot-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("usr")).unwrap();
+        std::fs::create_dir_all(root.join("var")).unwrap();
+        symlink("/usr/etc", root.join("etc")).unwrap();
+        symlink("../run", root.join("var/run")).unwrap();
+        symlink("/pathos-sysroot-loop", root.join("pathos-sysroot-loop")).unwrap();
+
+        let sysroot = Sysroot::new(&root);
+        assert_eq!(sysroot.path("/etc/app").host(), root.join("usr/etc/app"));
+        assert_eq!(sysroot.path("/var/run/app").host(), root.join("run/app"));
+
+        let dirs = sysroot.app_dirs(Platform::Linux.system_app_layout(None, "app").unwrap());
+        dirs.create().unwrap();
+        assert!(root.join("usr/etc/app").is_dir());
+
+        let looped = sysroot.path("/pathos-sysroot-loop/app");
+        assert!(looped.host().starts_with(&root));
+
+        let dir = PathBuf::from("/pathos-sysroot-loop/app");
+        let dirs = sysroot.app_dirs(AppDirs::new(
+            dir.clone(),
+            dir.clone(),
+            dir.clone(),
+            dir.clone(),
+            dir.clone(),
+            dir,
+        ));
+        assert!(dirs.create().is_err());
+        assert!(!Path::new("/pathos-sysroot-loop").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
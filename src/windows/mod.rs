@@ -1,12 +1,18 @@
+//! Directories on Windows. The native functions are only available when
+//! compiling for Windows; elsewhere, the `windows` feature leaves this module
+//! empty, and `Platform::Windows.user_layout` computes the same layout from
+//! given roots.
+
+/// Looks up a known folder. Nothing is cached here; callers keep the result
+/// in a `Resolver` so that a failed lookup can be retried.
+#[cfg(windows)]
 macro_rules! windows_path {
-    ($folderid:path) => {{
-        static CELL: once_cell::sync::OnceCell<Option<PathBuf>> = once_cell::sync::OnceCell::new();
-        CELL.get_or_init(|| match windirs::known_folder_path($folderid) {
-            Ok(path) => Some(path),
-            Err(_) => None,
-        })
-    }};
+    ($folderid:path, $name:expr) => {
+        windirs::known_folder_path($folderid).map_err(|_| crate::Error::NotFound($name))
+    };
 }
 
+#[cfg(windows)]
 pub mod system;
+#[cfg(windows)]
 pub mod user;
@@ -1,130 +1,103 @@
-use crate::Error;
+use crate::{AppDirs, Error, Platform, Resolver};
 use std::path::{Path, PathBuf};
 
+static PROGRAM_DATA: Resolver<PathBuf> = Resolver::new(resolve_program_data_dir);
+
 #[inline]
-fn config_dir() -> Result<&'static Path, Error> {
-    windows_path!(windirs::FolderId::ProgramData)
-        .as_ref()
-        .map(|x| &**x)
-        .ok_or_else(|| Error::NotFound("ProgramData"))
+fn resolve_program_data_dir() -> Result<PathBuf, Error> {
+    windows_path!(windirs::FolderId::ProgramData, "ProgramData")
 }
 
-pub struct AppDirs {
-    data_dir: PathBuf,
-    config_dir: PathBuf,
-    cache_dir: PathBuf,
-    log_dir: PathBuf,
-    temporary_dir: PathBuf,
+#[inline]
+pub fn program_data_dir() -> Result<&'static Path, Error> {
+    PROGRAM_DATA.get().map(|x| &**x)
 }
 
-impl crate::AppDirs for AppDirs {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-    {
-        let prefix = prefix.into();
-
-        let config_dir = config_dir()?;
-
-        let data_dir = config_dir.join(&prefix);
-        let cache_dir = data_dir.join("cache");
-
-        let user_dirs = Self {
-            config_dir: data_dir.join("config"),
-            log_dir: data_dir.join("log"),
-            temporary_dir: cache_dir.join("tmp"),
-            data_dir,
-            cache_dir,
-        };
-
-        user_dirs.create()?;
-
-        Ok(user_dirs)
-    }
-
-    fn create(&self) -> Result<(), Error> {
-        let dirs = [
-            self.data_dir(),
-            self.config_dir(),
-            self.cache_dir(),
-            self.temporary_dir(),
-            self.log_dir(),
-        ];
-
-        for dir in dirs.iter() {
-            std::fs::create_dir_all(dir).map_err(|e| {
-                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
-            })?;
-        }
-        // TODO: set tmp writable only by creator.
-
-        Ok(())
-    }
-
-    fn data_dir(&self) -> &Path {
-        &self.data_dir
-    }
-
-    fn config_dir(&self) -> &Path {
-        &self.config_dir
-    }
-
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
-    }
-
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
-    }
+/// The system-wide directories for `prefix`, without creating them.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    Platform::Windows
+        .system_app_layout(Some(program_data_dir()?), prefix)
+        .ok_or(Error::NotFound("System directories"))
+}
 
-    fn temporary_dir(&self) -> &Path {
-        &self.temporary_dir
-    }
+#[inline]
+pub fn data_dir() -> Result<&'static Path, Error> {
+    program_data_dir()
 }
 
 #[inline]
 pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-    config_dir().map(|x| x.join(prefix))
+    app_layout(prefix).map(|x| x.data_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-    app_data_dir(prefix).map(|x| x.join("config"))
+    app_layout(prefix).map(|x| x.config_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-    app_data_dir(prefix).map(|x| x.join("cache"))
+    app_layout(prefix).map(|x| x.cache_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-    app_data_dir(prefix).map(|x| x.join("log"))
+    app_layout(prefix).map(|x| x.log_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
+    app_layout(prefix).map(|x| x.state_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-    app_cache_dir(prefix).map(|x| x.join("tmp"))
+    app_layout(prefix).map(|x| x.temporary_dir().to_path_buf())
 }
 
 pub mod iri {
-    use crate::path::absolute::AbsolutePathBufExt;
-    use crate::Error;
+    use crate::{iri::dir_iri, Error};
     use iref::IriBuf;
     use std::path::Path;
 
     #[inline]
-    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_temporary_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+    pub fn program_data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::program_data_dir()?)
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::data_dir()?)
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_data_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_config_dir(prefix)?)
     }
 
     #[inline]
     pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_cache_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_cache_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_log_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_state_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_temporary_dir(prefix)?)
     }
 }
 
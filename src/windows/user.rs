@@ -1,35 +1,92 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::{AppDirs as _, Error, UserDirs};
 use once_cell::sync::Lazy;
 
-static DIRS: Lazy<Result<Dirs, Error>> = Lazy::new(|| Dirs::new());
+use crate::{
+    diagnose::{self, Provenance, Source},
+    layout::UserLayout,
+    AppDirs, DirKind, Error, Platform, Registry, Resolver, UserDirs,
+};
 
+static DIRS: Resolver<Dirs> = Resolver::new(Dirs::new);
+
+/// The resolver behind the free functions in this module.
+#[inline]
+pub fn resolver() -> &'static Resolver<Dirs> {
+    &DIRS
+}
+
+/// Resolves the user's directories again. Paths returned earlier remain
+/// valid. This also forgets the app directories cached by `app_dirs`.
 #[inline]
-pub(crate) fn home_dir() -> Result<&'static Path, Error> {
-    windows_path!(windirs::FolderId::Profile)
-        .as_ref()
-        .map(|x| &**x)
-        .ok_or_else(|| Error::NotFound("Home"))
+pub fn reload() -> Result<(), Error> {
+    DIRS.reload()?;
+    APPS.clear();
+    Ok(())
 }
 
+#[inline]
+pub fn home_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.home_dir())
+}
+
+/// How the app directory of each kind is chosen, for `AppDirs::explain`:
+/// that of the user directory it is laid out within.
+fn app_provenance(kind: DirKind) -> Provenance {
+    diagnose::app_provenance(Platform::Windows, kind, provenance)
+}
+
+/// How the directory of each kind is chosen, for `pathos::diagnose`. Apart
+/// from the profile itself, every directory is derived from it.
+pub(crate) fn provenance(kind: DirKind) -> Provenance {
+    match kind {
+        DirKind::Home => Provenance::new(Source::System),
+        // `GetTempPath` prefers `TMP`, then `TEMP`, then the profile.
+        DirKind::Temporary => match std::env::var_os("TMP") {
+            Some(_) => Provenance::env("TMP", Source::Default),
+            None => Provenance::env("TEMP", Source::Default),
+        },
+        _ => Provenance::default(),
+    }
+}
+
+static APPS: Lazy<Registry> = Lazy::new(|| Registry::new(|prefix| app_layout(prefix)));
+
+/// The registry behind `app_dirs` and the `app_*` functions in this module.
+#[inline]
+pub fn registry() -> &'static Registry {
+    &APPS
+}
+
+/// The directories for `prefix`, created on first use and shared after.
+#[inline]
+pub fn app_dirs<P: Into<PathBuf>>(prefix: P) -> Result<Arc<AppDirs>, Error> {
+    APPS.get(prefix)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Dirs {
-    home_dir: &'static Path,
+    home_dir: PathBuf,
     roaming_dir: PathBuf,
     local_dir: PathBuf,
+    programs_dir: PathBuf,
+    temporary_dir: PathBuf,
 }
 
 impl UserDirs for Dirs {
     fn new() -> Result<Self, Error> {
-        let home_dir = match home_dir() {
-            Ok(path) => path,
-            Err(e) => return Err(e.clone()),
-        };
+        let home_dir = windows_path!(windirs::FolderId::Profile, "Home")?;
         let appdata_dir = home_dir.join("AppData");
         let roaming_dir = appdata_dir.join("Roaming");
         let local_dir = appdata_dir.join("Local");
+        let programs_dir = local_dir.join("Programs");
 
         Ok(Self {
+            temporary_dir: std::env::temp_dir(),
+            programs_dir,
             local_dir,
             roaming_dir,
             home_dir,
@@ -40,6 +97,10 @@ impl UserDirs for Dirs {
         &self.home_dir
     }
 
+    fn config_dir(&self) -> &Path {
+        &self.roaming_dir
+    }
+
     fn data_dir(&self) -> &Path {
         &self.roaming_dir
     }
@@ -47,73 +108,23 @@ impl UserDirs for Dirs {
     fn cache_dir(&self) -> &Path {
         &self.local_dir
     }
-}
-
-pub struct AppDirs {
-    data_dir: PathBuf,
-    config_dir: PathBuf,
-    cache_dir: PathBuf,
-    log_dir: PathBuf,
-    temporary_dir: PathBuf,
-}
-
-impl crate::AppDirs for AppDirs {
-    fn new<P>(prefix: P) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-    {
-        let prefix = prefix.into();
-        let dirs = Dirs::new()?;
 
-        let data_dir = dirs.data_dir().join(&prefix);
-        let cache_dir = dirs.cache_dir().join(&prefix).join("cache");
-
-        let user_dirs = Self {
-            config_dir: data_dir.join("config"),
-            temporary_dir: cache_dir.join("tmp"),
-            log_dir: data_dir.join("log"),
-            data_dir,
-            cache_dir,
-        };
-
-        user_dirs.create()?;
-
-        Ok(user_dirs)
-    }
-
-    fn create(&self) -> Result<(), Error> {
-        let dirs = [
-            self.data_dir(),
-            self.config_dir(),
-            self.cache_dir(),
-            self.temporary_dir(),
-            self.log_dir(),
-        ];
-
-        for dir in dirs.iter() {
-            std::fs::create_dir_all(dir).map_err(|e| {
-                Error::CreateDirectoryFailed(eieio::Error::from(e), dir.to_path_buf())
-            })?;
-        }
-        // TODO: set tmp writable only by creator.
-
-        Ok(())
-    }
-
-    fn data_dir(&self) -> &Path {
-        &self.data_dir
+    fn state_dir(&self) -> &Path {
+        &self.local_dir
     }
 
-    fn config_dir(&self) -> &Path {
-        &self.config_dir
+    fn log_dir(&self) -> &Path {
+        &self.local_dir
     }
 
-    fn cache_dir(&self) -> &Path {
-        &self.cache_dir
+    /// Windows has no per-session runtime directory.
+    fn runtime_dir(&self) -> Option<&Path> {
+        None
     }
 
-    fn log_dir(&self) -> &Path {
-        &self.log_dir
+    /// `FOLDERID_UserProgramFiles`, where per-user installers put programs.
+    fn executables_dir(&self) -> Option<&Path> {
+        Some(&self.programs_dir)
     }
 
     fn temporary_dir(&self) -> &Path {
@@ -121,6 +132,14 @@ impl crate::AppDirs for AppDirs {
     }
 }
 
+/// The current user's directories for `prefix`, without creating them.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    let user = UserLayout::of(DIRS.get()?);
+    Ok(Platform::Windows
+        .app_layout(&user, prefix)
+        .with_provenance(app_provenance))
+}
+
 #[inline]
 pub fn roaming_dir() -> Result<&'static Path, Error> {
     data_dir()
@@ -136,54 +155,159 @@ pub fn data_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.data_dir())
 }
 
+#[inline]
+pub fn config_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.config_dir())
+}
+
 #[inline]
 pub fn cache_dir() -> Result<&'static Path, Error> {
     dir!(|x| x.cache_dir())
 }
 
+#[inline]
+pub fn state_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.state_dir())
+}
+
+#[inline]
+pub fn log_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.log_dir())
+}
+
+#[inline]
+pub fn runtime_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.runtime_dir())?.ok_or(Error::NotFound("Runtime"))
+}
+
+#[inline]
+pub fn executables_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.executables_dir())?.ok_or(Error::NotFound("Executables"))
+}
+
+#[inline]
+pub fn temporary_dir() -> Result<&'static Path, Error> {
+    dir!(|x| x.temporary_dir())
+}
+
 #[inline]
 pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.data_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.data_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.config_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.config_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.log_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.log_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.cache_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.cache_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.state_dir().to_path_buf())
 }
 
 #[inline]
 pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
-    AppDirs::new(prefix).map(|x| x.temporary_dir().to_path_buf())
+    app_dirs(prefix).map(|x| x.temporary_dir().to_path_buf())
 }
 
 pub mod iri {
-    use crate::path::absolute::AbsolutePathBufExt;
-    use crate::Error;
+    use crate::{iri::dir_iri, Error};
     use iref::IriBuf;
     use std::path::PathBuf;
 
+    #[inline]
+    pub fn home_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::home_dir()?)
+    }
+
+    #[inline]
+    pub fn roaming_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::roaming_dir()?)
+    }
+
+    #[inline]
+    pub fn local_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::local_dir()?)
+    }
+
+    #[inline]
+    pub fn data_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::data_dir()?)
+    }
+
+    #[inline]
+    pub fn cache_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::cache_dir()?)
+    }
+
+    #[inline]
+    pub fn config_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::config_dir()?)
+    }
+
+    #[inline]
+    pub fn state_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::state_dir()?)
+    }
+
+    #[inline]
+    pub fn log_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::log_dir()?)
+    }
+
+    #[inline]
+    pub fn runtime_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::runtime_dir()?)
+    }
+
+    #[inline]
+    pub fn executables_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::executables_dir()?)
+    }
+
+    #[inline]
+    pub fn temporary_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::temporary_dir()?)
+    }
+
+    #[inline]
+    pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_data_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_config_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_log_dir(prefix)?)
+    }
+
     #[inline]
     pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_cache_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_cache_dir(prefix)?)
+    }
+
+    #[inline]
+    pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
+        dir_iri(super::app_state_dir(prefix)?)
     }
 
     #[inline]
     pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<IriBuf, Error> {
-        Ok(super::app_temporary_dir(prefix)?
-            .to_absolute_path_buf()?
-            .to_file_iri()?)
+        dir_iri(super::app_temporary_dir(prefix)?)
     }
 }
 
@@ -1,6 +1,11 @@
-use crate::Error;
+use crate::diagnose::{self, Provenance, Source};
+use crate::layout::Roots;
+use crate::sysroot::{Rooted, Sysroot};
+use crate::{AppDirs, DirKind, Error, Platform, Registry};
+use once_cell::sync::Lazy;
 use std::env::{split_paths, var_os};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[inline(always)]
 pub fn home_dir() -> Result<PathBuf, Error> {
@@ -11,7 +16,7 @@ pub fn home_dir() -> Result<PathBuf, Error> {
 #[inline(always)]
 pub fn data_home() -> Result<PathBuf, Error> {
     let value = var_os("XDG_DATA_HOME")
-        .map(|x| PathBuf::from(x))
+        .map(PathBuf::from)
         .filter(|x| x.is_absolute());
 
     match value {
@@ -23,7 +28,7 @@ pub fn data_home() -> Result<PathBuf, Error> {
 #[inline(always)]
 pub fn config_home() -> Result<PathBuf, Error> {
     let value = var_os("XDG_CONFIG_HOME")
-        .map(|x| PathBuf::from(x))
+        .map(PathBuf::from)
         .filter(|x| x.is_absolute());
 
     match value {
@@ -32,29 +37,61 @@ pub fn config_home() -> Result<PathBuf, Error> {
     }
 }
 
+#[inline(always)]
+pub fn state_home() -> Result<PathBuf, Error> {
+    let value = var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute());
+
+    match value {
+        Some(v) => Ok(v),
+        None => home_dir().map(|x| x.join(".local/state")),
+    }
+}
+
+/// `~/.local/bin`, where the XDG spec expects user-specific executables. It
+/// has no environment variable of its own.
+#[inline(always)]
+pub fn executables_home() -> Result<PathBuf, Error> {
+    home_dir().map(|x| x.join(".local/bin"))
+}
+
+const DEFAULT_DATA_DIRS: &[&str] = &["/usr/local/share/", "/usr/share/"];
+const DEFAULT_CONFIG_DIRS: &[&str] = &["/etc/xdg/"];
+
 #[inline(always)]
 pub fn data_dirs() -> Vec<PathBuf> {
     var_os("XDG_DATA_DIRS")
         .map(|x| split_paths(&x).filter(|x| x.is_absolute()).collect())
-        .unwrap_or_else(|| {
-            vec![
-                PathBuf::from("/usr/local/share/"),
-                PathBuf::from("/usr/share/"),
-            ]
-        })
+        .unwrap_or_else(|| DEFAULT_DATA_DIRS.iter().map(PathBuf::from).collect())
 }
 
 #[inline(always)]
 pub fn config_dirs() -> Vec<PathBuf> {
     var_os("XDG_CONFIG_DIRS")
         .map(|x| split_paths(&x).filter(|x| x.is_absolute()).collect())
-        .unwrap_or_else(|| vec![PathBuf::from("/etc/xdg/")])
+        .unwrap_or_else(|| DEFAULT_CONFIG_DIRS.iter().map(PathBuf::from).collect())
+}
+
+/// `data_dirs` of the image at `sysroot`. The host's `XDG_DATA_DIRS` says
+/// nothing about the image, so the spec's defaults are used.
+pub fn data_dirs_in(sysroot: &Sysroot) -> Vec<Rooted> {
+    DEFAULT_DATA_DIRS.iter().map(|x| sysroot.path(x)).collect()
+}
+
+/// `config_dirs` of the image at `sysroot`. The host's `XDG_CONFIG_DIRS` says
+/// nothing about the image, so the spec's defaults are used.
+pub fn config_dirs_in(sysroot: &Sysroot) -> Vec<Rooted> {
+    DEFAULT_CONFIG_DIRS
+        .iter()
+        .map(|x| sysroot.path(x))
+        .collect()
 }
 
 #[inline(always)]
 pub fn cache_home() -> Result<PathBuf, Error> {
     let value = var_os("XDG_CACHE_HOME")
-        .map(|x| PathBuf::from(x))
+        .map(PathBuf::from)
         .filter(|x| x.is_absolute());
 
     match value {
@@ -63,7 +100,7 @@ pub fn cache_home() -> Result<PathBuf, Error> {
     }
 }
 
-#[cfg(unix)]
+#[cfg(any(unix, target_os = "wasi"))]
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum RuntimeDirError {
     #[error("XDG_RUNTIME_DIR environment variable is not defined.")]
@@ -72,7 +109,7 @@ pub enum RuntimeDirError {
     #[error("Could not resolve metadata for XDG_RUNTIME_DIR.")]
     Metadata(#[from] eieio::Error),
 
-    #[error("Invalid ownership. Requires ownership by {}, got: {0}.")]
+    #[error("Invalid ownership. Requires ownership by {0}, got: {1}.")]
     InvalidOwnership(u32, u32),
 
     #[error("Invalid access mode. Requires 0700, got {0:o}.")]
@@ -83,10 +120,17 @@ pub enum RuntimeDirError {
 #[inline(always)]
 pub fn runtime_dir() -> Result<PathBuf, RuntimeDirError> {
     let dir = var_os("XDG_RUNTIME_DIR")
-        .map(|x| PathBuf::from(x))
+        .map(PathBuf::from)
         .filter(|x| x.is_absolute())
         .ok_or_else(|| RuntimeDirError::Undefined)?;
 
+    check_runtime_dir(dir)
+}
+
+/// Checks that `dir` is owned by the current user and accessible only by
+/// them, as the XDG Base Directory specification requires.
+#[cfg(unix)]
+fn check_runtime_dir(dir: PathBuf) -> Result<PathBuf, RuntimeDirError> {
     use std::os::unix::fs::MetadataExt;
 
     let meta = std::fs::metadata(&dir).map_err(eieio::Error::from)?;
@@ -96,34 +140,197 @@ pub fn runtime_dir() -> Result<PathBuf, RuntimeDirError> {
         return Err(RuntimeDirError::InvalidOwnership(uid, meta.uid()));
     }
 
-    if meta.mode() != 0o700 {
-        return Err(RuntimeDirError::InvalidAccessMode(meta.mode()));
+    // `st_mode` also holds the file type, which isn't part of the check.
+    let mode = meta.mode() & 0o777;
+    if mode != 0o700 {
+        return Err(RuntimeDirError::InvalidAccessMode(mode));
     }
 
     Ok(dir)
 }
 
-// #[inline]
-// pub fn app_data_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-//     data_home().map(|x| x.join(prefix.as_ref()))
-// }
+/// WASI has no users, so only the variable and the directory's existence are
+/// checked.
+#[cfg(target_os = "wasi")]
+#[inline(always)]
+pub fn runtime_dir() -> Result<PathBuf, RuntimeDirError> {
+    let dir = var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .ok_or_else(|| RuntimeDirError::Undefined)?;
+
+    std::fs::metadata(&dir).map_err(eieio::Error::from)?;
+
+    Ok(dir)
+}
+
+pub mod iri {
+    use crate::{iri::dir_iri, Error};
+    use iref::IriBuf;
+
+    #[inline]
+    pub fn home_dir() -> Result<IriBuf, Error> {
+        dir_iri(super::home_dir()?)
+    }
+
+    #[inline]
+    pub fn data_home() -> Result<IriBuf, Error> {
+        dir_iri(super::data_home()?)
+    }
+
+    #[inline]
+    pub fn config_home() -> Result<IriBuf, Error> {
+        dir_iri(super::config_home()?)
+    }
 
-// #[inline]
-// pub fn app_config_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-//     config_home().map(|x| x.join(prefix.as_ref()))
-// }
+    #[inline]
+    pub fn cache_home() -> Result<IriBuf, Error> {
+        dir_iri(super::cache_home()?)
+    }
 
-// #[inline]
-// pub fn app_log_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-//     data_home().map(|x| x.join(prefix.as_ref()).join("log"))
-// }
+    #[inline]
+    pub fn state_home() -> Result<IriBuf, Error> {
+        dir_iri(super::state_home()?)
+    }
 
-// #[inline]
-// pub fn app_cache_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-//     cache_home().map(|x| x.join(prefix.as_ref()))
-// }
+    #[inline]
+    pub fn executables_home() -> Result<IriBuf, Error> {
+        dir_iri(super::executables_home()?)
+    }
+
+    #[inline]
+    pub fn data_dirs() -> Result<Vec<IriBuf>, Error> {
+        super::data_dirs().iter().map(dir_iri).collect()
+    }
 
-// #[inline]
-// pub fn app_temporary_dir<P: AsRef<Path>>(prefix: P) -> Result<PathBuf, Error> {
-//     cache_home().map(|x| x.join(prefix.as_ref()).join("tmp"))
-// }
+    #[inline]
+    pub fn config_dirs() -> Result<Vec<IriBuf>, Error> {
+        super::config_dirs().iter().map(dir_iri).collect()
+    }
+}
+
+#[cfg(any(unix, target_os = "wasi"))]
+pub(crate) fn runtime_dir_provenance() -> Provenance {
+    match runtime_dir() {
+        Ok(_) => Provenance::env("XDG_RUNTIME_DIR", Source::Default),
+        Err(e) => Provenance {
+            source: Source::Default,
+            raw: var_os("XDG_RUNTIME_DIR"),
+            rejected: Some(e.to_string()),
+        },
+    }
+}
+
+/// The current user's XDG directories for `prefix`, without creating them,
+/// on any platform. This is the layout `pathos::linux::user` uses.
+pub fn app_layout<P: AsRef<Path>>(prefix: P) -> Result<AppDirs, Error> {
+    let mut roots = Roots::new(home_dir()?);
+    roots
+        .set(DirKind::Config, config_home()?)
+        .set(DirKind::Data, data_home()?)
+        .set(DirKind::Cache, cache_home()?)
+        .set(DirKind::State, state_home()?);
+
+    let user = Platform::Linux.user_layout(&roots);
+    Ok(Platform::Linux
+        .app_layout(&user, prefix)
+        .with_provenance(app_provenance))
+}
+
+/// How the XDG base directory of each kind is chosen, for
+/// `pathos::diagnose`. Kinds the specification doesn't cover have no
+/// provenance here.
+pub(crate) fn provenance(kind: DirKind) -> Provenance {
+    match kind {
+        DirKind::Config => Provenance::env("XDG_CONFIG_HOME", Source::Default),
+        DirKind::Data => Provenance::env("XDG_DATA_HOME", Source::Default),
+        DirKind::Cache => Provenance::env("XDG_CACHE_HOME", Source::Default),
+        DirKind::State | DirKind::Log => Provenance::env("XDG_STATE_HOME", Source::Default),
+        _ => Provenance::default(),
+    }
+}
+
+/// How the app directory of each kind is chosen, for `AppDirs::explain`:
+/// that of the base directory it is laid out within.
+pub(crate) fn app_provenance(kind: DirKind) -> Provenance {
+    diagnose::app_provenance(Platform::Linux, kind, provenance)
+}
+
+static APPS: Lazy<Registry> = Lazy::new(|| Registry::new(|prefix| app_layout(prefix)));
+
+/// The registry behind `app_dirs` and the `app_*` functions in this module.
+#[inline]
+pub fn registry() -> &'static Registry {
+    &APPS
+}
+
+/// The directories for `prefix`, created on first use and shared after.
+#[inline]
+pub fn app_dirs<P: Into<PathBuf>>(prefix: P) -> Result<Arc<AppDirs>, Error> {
+    APPS.get(prefix)
+}
+
+#[inline]
+pub fn app_data_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.data_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_config_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.config_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_log_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.log_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_cache_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.cache_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_state_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.state_dir().to_path_buf())
+}
+
+#[inline]
+pub fn app_temporary_dir<P: Into<PathBuf>>(prefix: P) -> Result<PathBuf, Error> {
+    app_dirs(prefix).map(|x| x.temporary_dir().to_path_buf())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_dir_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("pathos-runtime-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(check_runtime_dir(dir.clone()).unwrap(), dir);
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        match check_runtime_dir(dir.clone()) {
+            Err(RuntimeDirError::InvalidAccessMode(mode)) => assert_eq!(mode, 0o755),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn app_sources() {
+        assert_eq!(app_provenance(DirKind::Config), provenance(DirKind::Config));
+        assert_eq!(app_provenance(DirKind::Log), provenance(DirKind::Data));
+        assert_eq!(
+            app_provenance(DirKind::Temporary),
+            provenance(DirKind::Cache)
+        );
+        assert_eq!(app_provenance(DirKind::Runtime), Provenance::default());
+    }
+}